@@ -1,5 +1,8 @@
-use super::parser::Expr;
-use super::parser::{binop, num};
+use super::error::ThbcError;
+use super::parser::{Builtin, Expr, Function, Param, PrintItem, Program, Special, Stmt};
+use super::tokenizer::{RelOp, Span};
+use super::parser::{assign, binop, builtin, neg, num, special, var};
+use std::collections::HashMap;
 
 /**
  * thbc - Tar Heel Basic Calculator - DCGen
@@ -13,61 +16,1238 @@ use super::parser::{binop, num};
  */
 
 /**
- * Given a parser::Expr, to_dc should return a string representing the tree
- * in valid `dc` format with a `p` at the end. Each number and operator should
- * be separated by a space with no trailing space at the end after the `p`.
- *
- * You are encouraged to use helper functions and recursion where sensible.
+ * dc names each register with a single byte. Variables whose name is a
+ * single letter keep that letter as their register. Longer names are given
+ * the next free register from this pool, which leaves out the lowercase
+ * letters, whitespace and the brackets dc uses to delimit strings, and then
+ * from the bytes 0x80 to 0xFF. The generated code holds those as the chars
+ * U+0080 to U+00FF, which DcRunner writes out as single bytes.
+ */
+const REGISTER_POOL: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789!\"#$%&'()*+,-./:;<=>?@\\^_`{|}~";
+
+/**
+ * The register holding the macro comparisons run when their relation holds.
+ * Like every name dc_gen uses for itself it is not a valid identifier, so it
+ * can never be given to a variable as well.
+ */
+const REL_MACRO: &str = "(rel)";
+
+/**
+ * The register values that are no longer needed are popped into. Nothing
+ * ever loads from it.
+ */
+const DISCARD: &str = "(discard)";
+
+/**
+ * The register whose stack holds the macro of every loop that is running,
+ * the innermost one on top, so that inner loops never overwrite outer ones.
+ */
+const LOOP_MACRO: &str = "(loop)";
+
+/**
+ * The register holding `[2Q]`, which a loop macro runs to end the loop when
+ * its condition is 0. It leaves both itself and the loop macro.
+ */
+const LOOP_EXIT: &str = "(exit)";
+
+/**
+ * The register a function stores its return value in right before leaving,
+ * and the one telling the code after a loop that a return in it is under way.
+ */
+const RETURN_VALUE: &str = "(return)";
+const RETURNING: &str = "(returning)";
+
+/**
+ * The register counting the elements while an array is copied.
+ */
+const COUNTER: &str = "(counter)";
+
+/**
+ * The register whose stack holds the index of an element while it is
+ * updated.
  */
-pub fn to_dc(expr: &Expr) -> String {
-    let mut output: String = recur_to_dc(expr); //creates a string to return, calls helper since this would be hard to do recursivley in one function
-    output.push('p'); //adds the print onto the end
-    output //returns string
+const SUBSCRIPT: &str = "(subscript)";
+
+/**
+ * A DcGen translates syntax trees into dc programs. It remembers which
+ * register each name was given, so one DcGen must be used for every program
+ * fed to the same dc process.
+ */
+pub struct DcGen {
+    registers: HashMap<String, char>,
+    next_register: usize,
+    macro_depth: usize,
+    loop_frames: Option<usize>,
+    function_frames: Option<usize>,
+    signatures: HashMap<String, Vec<Param>>,
 }
 
-fn recur_to_dc(expr: &Expr) -> String {
-    let mut output: String = String::new(); //creates a new string
-    match expr { //sees if the expr is a binop or number
-        Expr::BinOp{lhs, op, rhs} => {
-            output.push_str(&recur_to_dc(lhs)); //moves down recursively until a number is found
-            output.push_str(&recur_to_dc(rhs)); //same but for the right side
-            output.push(*op); //adds the operator
-            output.push(' '); //adds a space
-        },
-        Expr::Num(num) => {
-            output.push_str(&num.to_string()); //adds the number to output
-            output.push(' '); //adds a space after each number
-        },
+impl DcGen {
+    pub fn new() -> DcGen {
+        DcGen {
+            registers: HashMap::new(),
+            next_register: 0,
+            macro_depth: 0,
+            loop_frames: None,
+            function_frames: None,
+            signatures: HashMap::new(),
+        }
+    }
+
+    /**
+     * Given a parser::Expr, to_dc should return a string representing the tree
+     * in valid `dc` format with a `p` at the end. Each number and operator should
+     * be separated by a space with no trailing space at the end after the `p`.
+     *
+     * You are encouraged to use helper functions and recursion where sensible.
+     */
+    pub fn to_dc(&mut self, expr: &Expr) -> Result<String, ThbcError> {
+        let mut output: String = self.recur_to_dc(expr)?; //creates a string to return, calls helper since this would be hard to do recursivley in one function
+        output.push('p'); //adds the print onto the end
+        Ok(output) //returns string
+    }
+
+    /**
+     * A program is the code of each of its statements in order. Statements
+     * that produce no code, like an empty block, leave no trace.
+     */
+    pub fn program_to_dc(&mut self, program: &Program) -> Result<String, ThbcError> {
+        self.stmts_to_dc(&program.stmts)
+    }
+
+    /**
+     * Statements that are bare expressions print their value. A top level
+     * assignment only stores into its target, so there is no trailing `p`.
+     */
+    pub fn stmt_to_dc(&mut self, stmt: &Stmt) -> Result<String, ThbcError> {
+        match stmt {
+            Stmt::Block(stmts) => self.stmts_to_dc(stmts),
+            Stmt::Expr(Expr::Assign { target, value, .. }) => {
+                let mut output = self.recur_to_dc(value)?;
+                output.push_str(&self.store_to_dc(target)?);
+                Ok(output)
+            }
+            Stmt::Expr(Expr::OpAssign { target, op, value, .. }) => self.op_assign_to_dc(target, *op, value, ""),
+            Stmt::Print(items) => {
+                let mut output = Vec::new();
+                for item in items {
+                    match item {
+                        PrintItem::Str(text) => output.extend(string_to_dc(text)),
+                        PrintItem::Expr(expr) => output.push(self.recur_to_dc(expr)? + "n"), //prints without a newline
+                    }
+                }
+                Ok(output.join(" "))
+            }
+            Stmt::Expr(expr) if self.function_frames.is_some() => {
+                //the caller finds the return value on top of the stack, so nothing else may stay there
+                let output = self.to_dc(expr)?;
+                Ok(format!("{} s{}", output, self.register(DISCARD)?))
+            }
+            Stmt::Expr(expr) => self.to_dc(expr),
+            Stmt::If { cond, then, otherwise } => {
+                let (then, reg) = self.nested(|gen| gen.stmt_to_dc(then))?;
+                let otherwise = match otherwise {
+                    Some(stmt) => self.nested(|gen| gen.stmt_to_dc(stmt))?.0,
+                    None => String::new(),
+                };
+                let discard = self.register(DISCARD)?;
+                let cond = self.recur_to_dc(cond)?;
+                // the else branch waits on the stack and is swapped for the
+                // then branch when the condition holds, so it runs only once
+                Ok(format!("[{}] {}0 [s{} [{}]] s{} !={} x", otherwise, cond, discard, then, reg, reg))
+            }
+            Stmt::While { cond, body, .. } => {
+                let cond = self.recur_to_dc(cond)?;
+                self.loop_to_dc(String::new(), Some(cond), body, String::new())
+            }
+            Stmt::For { init, cond, step, body, .. } => {
+                let init = match init {
+                    Some(init) => self.effect_to_dc(init)?,
+                    None => String::new(),
+                };
+                let cond = match cond {
+                    Some(cond) => Some(self.recur_to_dc(cond)?),
+                    None => None,
+                };
+                let step = match step {
+                    Some(step) => self.effect_to_dc(step)?,
+                    None => String::new(),
+                };
+                self.loop_to_dc(init, cond, body, step)
+            }
+            Stmt::Break => Ok(format!("{}Q", constant_to_dc(self.frames_in_loop() + 1))), //also leaves the loop macro
+            Stmt::Continue => Ok(format!("{}Q", constant_to_dc(self.frames_in_loop()))),
+            Stmt::Define(function) => self.function_to_dc(function),
+            Stmt::Return(value) => {
+                let mut output = match value {
+                    Some(value) => self.recur_to_dc(value)?,
+                    None => String::from("0 "),
+                };
+                output.push_str(&format!("s{} ", self.register(RETURN_VALUE)?));
+                output.push_str(&self.escape_to_dc()?);
+                Ok(output)
+            }
+        }
+    }
+
+    /**
+     * dc has no jumps, so a loop is a macro that checks the condition, runs
+     * the body as a macro of its own, runs the step and then runs itself
+     * again. It is pushed onto the stack of the loop register while it runs.
+     * A continue quits out of the body macro, and a break out of the loop
+     * macro as well. `init` and `step` must leave the stack as they found it.
+     */
+    fn loop_to_dc(
+        &mut self,
+        init: String,
+        cond: Option<String>,
+        body: &Stmt,
+        step: String,
+    ) -> Result<String, ThbcError> {
+        let reg = self.register(LOOP_MACRO)?;
+        let exit = self.register(LOOP_EXIT)?;
+        let discard = self.register(DISCARD)?;
+        let check = match cond {
+            Some(cond) => format!("{}0 ={} ", cond, exit),
+            None => String::new(),
+        };
+        let outer = self.loop_frames.replace(0);
+        let body = self.nested(|gen| gen.stmt_to_dc(body));
+        self.loop_frames = outer;
+        let (body, _) = body?;
+        let mut output = format!(
+            "{}[2Q] s{} [{}[{}] x {}l{} x] S{} l{} x L{} s{}",
+            init, exit, check, body, step, reg, reg, reg, reg, discard
+        );
+        if self.function_frames.is_some() {
+            //a return in the body only got as far as here
+            let returning = self.register(RETURNING)?;
+            let (escape, reg) = self.nested(|gen| gen.escape_to_dc())?;
+            output.push_str(&format!(" l{} 0 [0 s{} {}] s{} !={}", returning, returning, escape, reg, reg));
+        }
+        Ok(output)
+    }
+
+    /**
+     * dc code that leaves the function being generated once its return value
+     * is stored. Each iteration of a loop is a macro run by the one before,
+     * so there is no telling how many macros to quit from inside of a loop.
+     * There a return sets the returning register and breaks out of the loop
+     * instead, and the code after the loop carries on from there.
+     */
+    fn escape_to_dc(&mut self) -> Result<String, ThbcError> {
+        match self.loop_frames {
+            Some(frames) => Ok(format!("1 s{} {}Q", self.register(RETURNING)?, constant_to_dc(frames + 1))),
+            None => {
+                let frames = self.function_frames.expect("the parser only allows return inside of functions");
+                Ok(format!("{}Q", constant_to_dc(frames)))
+            }
+        }
+    }
+
+    /**
+     * dc code for an expression that is only evaluated for its effect, like
+     * the first and last ones of a for loop. Nothing is left on the stack.
+     */
+    fn effect_to_dc(&mut self, expr: &Expr) -> Result<String, ThbcError> {
+        match expr {
+            Expr::Assign { target, value, .. } => {
+                let mut output = self.recur_to_dc(value)?;
+                output.push_str(&self.store_to_dc(target)?);
+                output.push(' ');
+                Ok(output)
+            }
+            Expr::OpAssign { target, op, value, .. } => Ok(self.op_assign_to_dc(target, *op, value, "")? + " "),
+            Expr::IncDec { target, op, .. } => Ok(self.update_to_dc(target, &format!("1{} ", op))? + " "),
+            _ => {
+                let mut output = self.recur_to_dc(expr)?;
+                output.push_str(&format!("s{} ", self.register(DISCARD)?)); //pops the unused value
+                Ok(output)
+            }
+        }
+    }
+
+    /**
+     * A function is a macro in a register of its own. It pops its arguments
+     * into the stacks of its parameters' registers with `S`, pushes 0 for
+     * each auto and runs its body as a macro, which stores the return value
+     * in a register. Then it loads that value and pops its parameters and
+     * autos again with `L`. A return quits out of the body macro, so that
+     * this always happens.
+     *
+     * dc can't put arrays on the stack, so the caller copies each array it
+     * passes into the transfer array for its position, and the function
+     * copies it from there onto the stack of its parameter's register.
+     */
+    fn function_to_dc(&mut self, function: &Function) -> Result<String, ThbcError> {
+        self.signatures.insert(function.name.clone(), function.params.clone()); //it may call itself
+        let discard = self.register(DISCARD)?;
+        let mut prologue = String::new();
+        for param in function.params.iter().rev() {
+            if let Param::Var(name) = param {
+                prologue.push_str(&format!("S{} ", self.register(name)?)); //the last argument is on top
+            }
+        }
+        for (position, param) in function.params.iter().enumerate() {
+            if let Param::Array(name) = param {
+                let (array, length) = self.array_registers(name)?;
+                let (transfer, transfer_length) = self.array_registers(&transfer_name(position))?;
+                prologue.push_str(&format!("0 S{} l{} S{} ", array, transfer_length, length));
+                prologue.push_str(&self.copy_to_dc(transfer, array, length)?);
+            }
+        }
+        for auto in &function.autos {
+            match auto {
+                Param::Var(name) => prologue.push_str(&format!("0 S{} ", self.register(name)?)),
+                Param::Array(name) => {
+                    let (array, length) = self.array_registers(name)?;
+                    prologue.push_str(&format!("0 S{} 0 S{} ", array, length));
+                }
+            }
+        }
+        let mut epilogue = String::new();
+        for local in function.params.iter().chain(&function.autos) {
+            match local {
+                Param::Var(name) => epilogue.push_str(&format!(" L{} s{}", self.register(name)?, discard)),
+                Param::Array(name) => {
+                    let (array, length) = self.array_registers(name)?;
+                    epilogue.push_str(&format!(" L{} s{} L{} s{}", array, discard, length, discard));
+                }
+            }
+        }
+        let outer = (self.function_frames.replace(0), self.loop_frames.take());
+        let body = self.nested(|gen| gen.stmts_to_dc(&function.body));
+        self.function_frames = outer.0;
+        self.loop_frames = outer.1;
+        let (mut body, _) = body?;
+        if !body.is_empty() {
+            body.push(' ');
+        }
+        let value = self.register(RETURN_VALUE)?;
+        let reg = self.register(&format!("{}()", function.name))?;
+        Ok(format!("[{}[{}0 s{}] x l{}{}] s{}", prologue, body, value, value, epilogue, reg))
+    }
+
+    /**
+     * Pushes the arguments of a call in order and runs the function. Arrays
+     * are copied into the transfer arrays right before, when no other call
+     * can overwrite them anymore.
+     */
+    fn call_to_dc(&mut self, name: &str, args: &[Expr], span: Span) -> Result<String, ThbcError> {
+        if let Some(params) = self.signatures.get(name) {
+            //a function may also be defined after the functions calling it
+            let message = if params.len() != args.len() {
+                Some(format!("Function {} takes {} arguments, not {}", name, params.len(), args.len()))
+            } else {
+                params.iter().zip(args).enumerate().find_map(|(position, param)| match param {
+                    (Param::Var(_), Expr::Array(_)) => {
+                        Some(format!("Argument {} of {} can't be an array", position + 1, name))
+                    }
+                    (Param::Array(_), Expr::Array(_)) => None,
+                    (Param::Array(_), _) => Some(format!("Argument {} of {} must be an array", position + 1, name)),
+                    _ => None,
+                })
+            };
+            if let Some(message) = message {
+                return Err(ThbcError::Runtime { message, span });
+            }
+        }
+        let mut output = String::new();
+        for arg in args {
+            if let Expr::Array(_) = arg {
+                continue;
+            }
+            output.push_str(&self.recur_to_dc(arg)?);
+        }
+        for (position, arg) in args.iter().enumerate() {
+            if let Expr::Array(array) = arg {
+                let (array, length) = self.array_registers(array)?;
+                let (transfer, transfer_length) = self.array_registers(&transfer_name(position))?;
+                output.push_str(&format!("l{} s{} ", length, transfer_length));
+                output.push_str(&self.copy_to_dc(array, transfer, transfer_length)?);
+            }
+        }
+        output.push_str(&format!("l{} x ", self.register(&format!("{}()", name))?));
+        Ok(output)
+    }
+
+    /**
+     * dc code copying the first `length` elements of one array register into
+     * another, one at a time.
+     */
+    fn copy_to_dc(&mut self, from: char, to: char, length: char) -> Result<String, ThbcError> {
+        let counter = self.register(COUNTER)?;
+        let reg = self.macro_register()?;
+        Ok(format!(
+            "0 s{c} [l{c};{} l{c}:{} l{c}1+ d s{c} l{} >{r}] s{r} 0 l{} >{r} ",
+            from,
+            to,
+            length,
+            length,
+            c = counter,
+            r = reg
+        ))
+    }
+
+    /**
+     * The registers holding an array and its length. dc can't tell how many
+     * elements an array has, so every store keeps the length up to date for
+     * when the array has to be copied.
+     */
+    fn array_registers(&mut self, name: &str) -> Result<(char, char), ThbcError> {
+        Ok((self.register(&format!("{}[]", name))?, self.register(&format!("{}[] length", name))?))
+    }
+
+    fn frames_in_loop(&self) -> usize {
+        self.loop_frames.expect("the parser only allows break and continue inside of loops")
+    }
+
+    fn stmts_to_dc(&mut self, stmts: &[Stmt]) -> Result<String, ThbcError> {
+        let mut codes = Vec::with_capacity(stmts.len());
+        for stmt in stmts {
+            let code = self.stmt_to_dc(stmt)?;
+            if !code.is_empty() {
+                codes.push(code);
+            }
+        }
+        Ok(codes.join(" "))
+    }
+
+    /**
+     * The dc command that pops the top of the stack into an assignable Expr.
+     * The parser only ever builds assignments to variables, array elements
+     * and special variables. An element's index is pushed on top of the value
+     * and the array's length grows to reach past it.
+     */
+    fn store_to_dc(&mut self, target: &Expr) -> Result<String, ThbcError> {
+        Ok(match target {
+            Expr::Index { name, index, .. } => self.recur_to_dc(index)? + &self.element_store_to_dc(name)?,
+            Expr::Var(name) => format!("s{}", self.register(name)?),
+            Expr::Special(Special::Scale) => String::from("k"),
+            Expr::Special(Special::Ibase) => String::from("i"),
+            Expr::Special(Special::Obase) => String::from("o"),
+            _ => unreachable!("not an assignable expression: {:?}", target),
+        })
+    }
+
+    fn element_store_to_dc(&mut self, name: &str) -> Result<String, ThbcError> {
+        let (array, length) = self.array_registers(name)?;
+        let (discard, reg) = (self.register(DISCARD)?, self.macro_register()?);
+        Ok(format!("d 1+ d l{} [d s{}] s{} <{} s{} :{}", length, length, reg, reg, discard, array))
+    }
+
+    /**
+     * dc code replacing the value of an assignable expression. `change`
+     * finds the old value on top of the stack and leaves the new one on top
+     * of whatever the expression is worth, if anything. An element's index
+     * waits on a register's stack meanwhile, so it is only evaluated once.
+     */
+    fn update_to_dc(&mut self, target: &Expr, change: &str) -> Result<String, ThbcError> {
+        match target {
+            Expr::Index { name, index, .. } => {
+                let index = self.recur_to_dc(index)?;
+                let (array, reg) = (self.array_registers(name)?.0, self.register(SUBSCRIPT)?);
+                let store = self.element_store_to_dc(name)?;
+                Ok(format!("{}S{} l{};{} {}L{} {}", index, reg, reg, array, change, reg, store))
+            }
+            _ => Ok(self.recur_to_dc(target)? + change + &self.store_to_dc(target)?),
+        }
+    }
+
+    /**
+     * The value goes first, just like in an assignment, and is swapped
+     * under the old value to apply the operator. `keep` is what the
+     * new value needs to stay on the stack as the value of the expression.
+     */
+    fn op_assign_to_dc(&mut self, target: &Expr, op: char, value: &Expr, keep: &str) -> Result<String, ThbcError> {
+        let value = self.recur_to_dc(value)?;
+        let (load, store) = match target {
+            Expr::Index { name, index, .. } => {
+                let index = self.recur_to_dc(index)?;
+                let (array, reg) = (self.array_registers(name)?.0, self.register(SUBSCRIPT)?);
+                let store = format!("L{} {}", reg, self.element_store_to_dc(name)?);
+                (format!("{}S{} l{};{} ", index, reg, reg, array), store)
+            }
+            _ => (self.recur_to_dc(target)?, self.store_to_dc(target)?),
+        };
+        Ok(format!("{}{}r{} {}{}", value, load, op, keep, store))
+    }
+
+    fn recur_to_dc(&mut self, expr: &Expr) -> Result<String, ThbcError> {
+        let mut output: String = String::new(); //creates a new string
+        match expr { //sees if the expr is a binop or number
+            Expr::BinOp{lhs, op, rhs, ..} => {
+                output.push_str(&self.recur_to_dc(lhs)?); //moves down recursively until a number is found
+                output.push_str(&self.recur_to_dc(rhs)?); //same but for the right side
+                output.push(*op); //adds the operator
+                output.push(' '); //adds a space
+            },
+            Expr::Neg(operand) => {
+                if let Expr::Num(num) = &**operand {
+                    output.push('_'); //dc spells negative literals with an underscore
+                    output.push_str(num);
+                    output.push(' ');
+                } else {
+                    output.push_str("0 "); //otherwise negate by subtracting from zero
+                    output.push_str(&self.recur_to_dc(operand)?);
+                    output.push_str("- ");
+                }
+            },
+            Expr::Num(num) => {
+                output.push_str(num); //adds the literal exactly as written to output
+                output.push(' '); //adds a space after each number
+            },
+            Expr::Var(name) => {
+                output.push('l'); //loads the variable's register onto the stack
+                output.push(self.register(name)?);
+                output.push(' ');
+            },
+            Expr::Special(Special::Scale) => {
+                output.push_str("K "); //pushes the current scale
+            },
+            Expr::Special(Special::Ibase) => {
+                output.push_str("I "); //pushes the current input base
+            },
+            Expr::Special(Special::Obase) => {
+                output.push_str("O "); //pushes the current output base
+            },
+            Expr::Builtin(Builtin::Scale, arg) => {
+                output.push_str(&self.recur_to_dc(arg)?);
+                output.push_str("X "); //replaces the value with its number of fraction digits
+            },
+            Expr::Rel{lhs, op, rhs} => {
+                let operands = self.recur_to_dc(lhs)? + &self.recur_to_dc(rhs)?;
+                output.push_str(&self.compare_to_dc(&operands, rel_to_dc(*op))?);
+            },
+            Expr::And(lhs, rhs) => {
+                let (body, reg) = self.macro_to_dc(rhs)?; //the rhs only runs inside the macro
+                output.push_str("0 "); //the result starts out as false
+                output.push_str(&self.recur_to_dc(lhs)?);
+                output.push_str(&format!("0 [{}+] s{} !={} ", body, reg, reg)); //adds the rhs's truth when the lhs holds
+            },
+            Expr::Or(lhs, rhs) => {
+                let (body, reg) = self.macro_to_dc(rhs)?;
+                output.push_str(&self.truth_to_dc(lhs)?);
+                output.push_str(&format!("d 0 [{}+] s{} ={} ", body, reg, reg)); //only asks the rhs when the lhs is false
+            },
+            Expr::Not(operand) => {
+                let operands = self.recur_to_dc(operand)? + "0 ";
+                output.push_str(&self.compare_to_dc(&operands, "=")?); //true exactly when the operand is 0
+            },
+            Expr::Call{name, args, span} => {
+                output.push_str(&self.call_to_dc(name, args, *span)?);
+            },
+            Expr::Index{name, index, ..} => {
+                output.push_str(&self.recur_to_dc(index)?);
+                output.push_str(&format!(";{} ", self.array_registers(name)?.0)); //loads the element
+            },
+            Expr::Array(name) => unreachable!("the parser only allows whole arrays as arguments: {}", name),
+            Expr::Assign{target, value, ..} => {
+                output.push_str(&self.recur_to_dc(value)?);
+                output.push_str("d "); //keeps a copy on the stack as the value of the assignment
+                output.push_str(&self.store_to_dc(target)?);
+                output.push(' ');
+            },
+            Expr::OpAssign{target, op, value, ..} => {
+                output.push_str(&self.op_assign_to_dc(target, *op, value, "d ")?);
+                output.push(' ');
+            },
+            Expr::IncDec{target, op, post, ..} => {
+                //a copy of the old value stays under the new one for x++, and of the new one for ++x
+                let change = if *post { format!("d 1{} ", op) } else { format!("1{} d ", op) };
+                output.push_str(&self.update_to_dc(target, &change)?);
+                output.push(' ');
+            },
+        }
+        Ok(output)
+    }
+
+    /**
+     * dc code for 1 if the two values pushed by `operands` satisfy the dc
+     * comparison `cmd` and 0 otherwise. A 0 goes under the operands and a
+     * macro adding 1 to it runs when the comparison holds.
+     */
+    fn compare_to_dc(&mut self, operands: &str, cmd: &str) -> Result<String, ThbcError> {
+        let reg = self.register(REL_MACRO)?;
+        Ok(format!("0 {}[1+] s{} {}{} ", operands, reg, cmd, reg))
+    }
+
+    /**
+     * dc code for 1 if expr is nonzero and 0 otherwise.
+     */
+    fn truth_to_dc(&mut self, expr: &Expr) -> Result<String, ThbcError> {
+        let operands = self.recur_to_dc(expr)? + "0 ";
+        self.compare_to_dc(&operands, "!=")
+    }
+
+    /**
+     * Generates the truth of expr as the body of a macro, along with the
+     * register to store the macro in right before it may run.
+     */
+    fn macro_to_dc(&mut self, expr: &Expr) -> Result<(String, char), ThbcError> {
+        self.nested(|gen| gen.truth_to_dc(expr))
+    }
+
+    /**
+     * Generates the body of a macro with `generate`, along with the register
+     * of the current depth. Macros nested inside the body are given the
+     * register of the next depth, so none of them can overwrite a macro that
+     * is still running.
+     */
+    fn nested<F>(&mut self, generate: F) -> Result<(String, char), ThbcError>
+    where
+        F: FnOnce(&mut DcGen) -> Result<String, ThbcError>,
+    {
+        let reg = self.macro_register()?;
+        self.macro_depth += 1;
+        //a break, continue or return in the body has one more macro to quit
+        for frames in self.loop_frames.iter_mut().chain(self.function_frames.iter_mut()) {
+            *frames += 1;
+        }
+        let body = generate(self);
+        for frames in self.loop_frames.iter_mut().chain(self.function_frames.iter_mut()) {
+            *frames -= 1;
+        }
+        self.macro_depth -= 1;
+        Ok((body?, reg))
+    }
+
+    fn macro_register(&mut self) -> Result<char, ThbcError> {
+        self.register(&format!("(macro {})", self.macro_depth))
+    }
+
+    /**
+     * Looks up the register holding a variable, handing out a new one the
+     * first time a long name is seen.
+     */
+    fn register(&mut self, name: &str) -> Result<char, ThbcError> {
+        if name.len() == 1 {
+            return Ok(name.chars().next().unwrap());
+        }
+        if let Some(reg) = self.registers.get(name) {
+            return Ok(*reg);
+        }
+        match REGISTER_POOL.chars().chain((0x80..=0xFF).map(char::from)).nth(self.next_register) {
+            Some(reg) => {
+                self.next_register += 1;
+                self.registers.insert(String::from(name), reg);
+                Ok(reg)
+            }
+            None => Err(ThbcError::Backend {
+                message: format!("Too many names for the dc backend: {}", name),
+            }),
+        }
+    }
+}
+
+/**
+ * dc commands printing a string. A dc string can't hold an unbalanced
+ * bracket, so brackets are printed from their character codes, and so are
+ * newlines to keep the code on one line. Bytes from 0x80 up name registers
+ * in the generated code, so other characters are printed from the number
+ * their UTF-8 bytes make up, which `P` prints as those bytes.
+ */
+fn string_to_dc(text: &str) -> Vec<String> {
+    let mut output = Vec::new();
+    let mut run = String::new();
+    for c in text.chars() {
+        match c {
+            '[' | ']' | '\n' | '\u{80}'.. => {
+                if !run.is_empty() {
+                    output.push(format!("[{}]P", run));
+                    run.clear();
+                }
+                let bytes = c.encode_utf8(&mut [0; 4]).bytes().fold(0, |code, byte| code * 256 + byte as usize);
+                output.push(format!("{}P", constant_to_dc(bytes)));
+            }
+            _ => run.push(c),
+        }
+    }
+    if !run.is_empty() {
+        output.push(format!("[{}]P", run));
     }
     output
 }
 
+/**
+ * dc reads numbers in its input base, which bc code can change with `ibase`.
+ * A single digit, `A` to `F` included, means the same in every base, so the
+ * constants the generated code relies on are built out of those.
+ */
+fn constant_to_dc(n: usize) -> String {
+    match n {
+        0..=9 => n.to_string(),
+        10..=15 => char::from(b'A' + (n - 10) as u8).to_string(),
+        _ => match n % 10 {
+            0 => format!("{} A*", constant_to_dc(n / 10)),
+            digit => format!("{} A*{}+", constant_to_dc(n / 10), digit),
+        },
+    }
+}
+
+/**
+ * The array a caller copies the array it passes as argument `position` into.
+ */
+fn transfer_name(position: usize) -> String {
+    format!("(argument {})", position)
+}
+
+/**
+ * dc compares the top of the stack, which is the rhs, with the value under
+ * it, so each relation is spelled with the operands flipped: `a < b` holds
+ * when the top is greater than the second.
+ */
+fn rel_to_dc(op: RelOp) -> &'static str {
+    match op {
+        RelOp::Lt => ">",
+        RelOp::Le => "!<",
+        RelOp::Gt => "<",
+        RelOp::Ge => "!>",
+        RelOp::Eq => "=",
+        RelOp::Ne => "!=",
+    }
+}
+
+impl Default for DcGen {
+    fn default() -> DcGen {
+        DcGen::new()
+    }
+}
+
 #[cfg(test)]
 mod to_dc {
     use super::*;
 
+    fn to_dc(expr: &Expr) -> String {
+        DcGen::new().to_dc(expr).unwrap()
+    }
+
+    fn stmt_to_dc(stmt: &Stmt) -> String {
+        DcGen::new().stmt_to_dc(stmt).unwrap()
+    }
+
     mod lvl4 {
         use super::*;
 
         #[test]
         fn dc_num() {
-            assert_eq!("1 p", to_dc(&num(1.0)));
+            assert_eq!("1 p", to_dc(&num("1")));
         }
 
         #[test]
         fn dc_binop() {
-            assert_eq!("1 2 * p", to_dc(&binop(num(1.0), '*', num(2.0))));
+            assert_eq!("1 2 * p", to_dc(&binop(num("1"), '*', num("2"))));
         }
 
         // TODO: Add additional tests
+
+        #[test]
+        fn dc_num_exact_text() {
+            assert_eq!("12345678901234567890 0.10 + p", to_dc(&binop(num("12345678901234567890"), '+', num("0.10"))));
+        }
         
+        #[test]
+        fn dc_mod() {
+            assert_eq!("7 2 % 3 * p", to_dc(&binop(binop(num("7"), '%', num("2")), '*', num("3"))));
+        }
+
         #[test]
         fn dc_multiple_ops() {
             assert_eq!(
                 "1 1 / 1 - p",
-                to_dc(&binop(binop(num(1.0), '/', num(1.0)), '-', num(1.0)))
+                to_dc(&binop(binop(num("1"), '/', num("1")), '-', num("1")))
             );
         }
         
     }
+
+    mod pow {
+        use super::*;
+
+        #[test]
+        fn dc_pow() {
+            assert_eq!("2 3 ^ p", to_dc(&binop(num("2"), '^', num("3"))));
+        }
+
+        #[test]
+        fn dc_pow_right_associative() {
+            assert_eq!(
+                "2 3 2 ^ ^ p",
+                to_dc(&binop(num("2"), '^', binop(num("3"), '^', num("2"))))
+            );
+        }
+    }
+
+    mod unary {
+        use super::*;
+
+        #[test]
+        fn dc_neg_literal() {
+            assert_eq!("_3 2 * p", to_dc(&binop(neg(num("3")), '*', num("2"))));
+        }
+
+        #[test]
+        fn dc_neg_expr() {
+            assert_eq!(
+                "2 0 1 1 + - * p",
+                to_dc(&binop(num("2"), '*', neg(binop(num("1"), '+', num("1")))))
+            );
+        }
+
+        #[test]
+        fn dc_double_neg() {
+            assert_eq!("0 _3 - p", to_dc(&neg(neg(num("3")))));
+        }
+    }
+
+    mod assign {
+        use super::super::super::parser::{index, op_assign, post, pre};
+        use super::*;
+
+        #[test]
+        fn dc_var() {
+            assert_eq!("lx 1 + p", to_dc(&binop(var("x"), '+', num("1"))));
+        }
+
+        #[test]
+        fn dc_assign_statement_does_not_print() {
+            assert_eq!(
+                "3 4 * sx",
+                stmt_to_dc(&Stmt::Expr(assign(var("x"), binop(num("3"), '*', num("4")))))
+            );
+        }
+
+        #[test]
+        fn dc_expr_statement_prints() {
+            assert_eq!("lx 1 + p", stmt_to_dc(&Stmt::Expr(binop(var("x"), '+', num("1")))));
+        }
+
+        #[test]
+        fn dc_assign_chain() {
+            assert_eq!("1 d sb sa", stmt_to_dc(&Stmt::Expr(assign(var("a"), assign(var("b"), num("1"))))));
+        }
+
+        #[test]
+        fn dc_assign_as_value() {
+            assert_eq!("2 d sa la * p", to_dc(&binop(assign(var("a"), num("2")), '*', var("a"))));
+        }
+
+        #[test]
+        fn dc_op_assign_statement_does_not_print() {
+            assert_eq!("2 lx r+ sx", stmt_to_dc(&Stmt::Expr(op_assign(var("x"), '+', num("2")))));
+        }
+
+        #[test]
+        fn dc_inc_dec_values() {
+            assert_eq!("lx d 1+ sx 2 * p", to_dc(&binop(post(var("x"), '+'), '*', num("2"))));
+            assert_eq!(
+                "1 ly r- d sy ly 1- d sy + p",
+                to_dc(&binop(op_assign(var("y"), '-', num("1")), '+', pre('-', var("y"))))
+            );
+        }
+
+        #[test]
+        fn dc_update_element_once() {
+            assert_eq!(
+                "li SC lC;A d 1+ LC d 1+ d lB [d sB] sE <E sD :A p",
+                to_dc(&post(index("a", var("i")), '+'))
+            );
+        }
+    }
+
+    mod scale {
+        use super::*;
+
+        #[test]
+        fn dc_scale_assign() {
+            assert_eq!("5 k", stmt_to_dc(&Stmt::Expr(assign(special(Special::Scale), num("5")))));
+        }
+
+        #[test]
+        fn dc_scale_assign_as_value() {
+            assert_eq!(
+                "2 d k 1 + p",
+                to_dc(&binop(assign(special(Special::Scale), num("2")), '+', num("1")))
+            );
+        }
+
+        #[test]
+        fn dc_scale_read() {
+            assert_eq!("K p", to_dc(&special(Special::Scale)));
+        }
+
+        #[test]
+        fn dc_scale_builtin() {
+            assert_eq!("1.25 X p", to_dc(&builtin(Builtin::Scale, num("1.25"))));
+        }
+    }
+
+    mod base {
+        use super::*;
+
+        #[test]
+        fn dc_base_assign() {
+            assert_eq!("16 o", stmt_to_dc(&Stmt::Expr(assign(special(Special::Obase), num("16")))));
+            assert_eq!("2 i", stmt_to_dc(&Stmt::Expr(assign(special(Special::Ibase), num("2")))));
+        }
+
+        #[test]
+        fn dc_base_read() {
+            assert_eq!("I O + p", to_dc(&binop(special(Special::Ibase), '+', special(Special::Obase))));
+        }
+
+        #[test]
+        fn dc_hex_literal() {
+            assert_eq!("FF 1 + p", to_dc(&binop(num("FF"), '+', num("1"))));
+        }
+    }
+
+    mod rel {
+        use super::super::super::parser::rel;
+        use super::*;
+
+        #[test]
+        fn dc_rel() {
+            assert_eq!("0 1 2 [1+] sA >A p", to_dc(&rel(num("1"), RelOp::Lt, num("2"))));
+        }
+
+        #[test]
+        fn dc_rel_operators_flip() {
+            let cases = [
+                (RelOp::Le, "!<"),
+                (RelOp::Gt, "<"),
+                (RelOp::Ge, "!>"),
+                (RelOp::Eq, "="),
+                (RelOp::Ne, "!="),
+            ];
+            for (op, cmd) in cases.iter() {
+                assert_eq!(format!("0 la lb [1+] sA {}A p", cmd), to_dc(&rel(var("a"), *op, var("b"))));
+            }
+        }
+
+        #[test]
+        fn dc_rel_nested_shares_macro() {
+            assert_eq!(
+                "0 0 1 2 [1+] sA >A 0 [1+] sA !=A p",
+                to_dc(&rel(rel(num("1"), RelOp::Lt, num("2")), RelOp::Ne, num("0")))
+            );
+        }
+    }
+
+    mod logic {
+        use super::super::super::parser::{and, not, or, rel};
+        use super::*;
+
+        #[test]
+        fn dc_not() {
+            assert_eq!("0 la 0 [1+] sA =A p", to_dc(&not(var("a"))));
+        }
+
+        #[test]
+        fn dc_and_runs_rhs_in_macro() {
+            assert_eq!(
+                "0 la 0 [0 lb 0 [1+] sB !=B +] sA !=A p",
+                to_dc(&and(var("a"), var("b")))
+            );
+        }
+
+        #[test]
+        fn dc_or_runs_rhs_in_macro() {
+            assert_eq!(
+                "0 la 0 [1+] sB !=B d 0 [0 lb 0 [1+] sB !=B +] sA =A p",
+                to_dc(&or(var("a"), var("b")))
+            );
+        }
+
+        #[test]
+        fn dc_nested_macros_get_own_registers() {
+            assert_eq!(
+                "0 la 0 [0 0 lb 0 [0 lc 0 [1+] sC !=C +] sB !=B 0 [1+] sC !=C +] sA !=A p",
+                to_dc(&and(var("a"), and(var("b"), var("c"))))
+            );
+        }
+
+        #[test]
+        fn dc_rel_in_logic() {
+            assert_eq!(
+                "0 0 1 2 [1+] sA >A 0 [1+] sA =A p",
+                to_dc(&not(rel(num("1"), RelOp::Lt, num("2"))))
+            );
+        }
+    }
+
+    mod program {
+        use super::super::super::parser::index;
+        use super::super::super::tokenizer::Span;
+        use super::*;
+
+        #[test]
+        fn dc_program() {
+            let program = Program {
+                stmts: vec![
+                    Stmt::Expr(assign(var("x"), num("2"))),
+                    Stmt::Block(vec![Stmt::Expr(var("x")), Stmt::Block(vec![])]),
+                    Stmt::Expr(binop(var("x"), '*', num("3"))),
+                ],
+            };
+            assert_eq!("2 sx lx p lx 3 * p", DcGen::new().program_to_dc(&program).unwrap());
+        }
+
+        #[test]
+        fn dc_if() {
+            let program = Program {
+                stmts: vec![Stmt::If {
+                    cond: var("x"),
+                    then: Box::new(Stmt::Expr(num("1"))),
+                    otherwise: None,
+                }],
+            };
+            assert_eq!("[] lx 0 [sB [1 p]] sA !=A x", DcGen::new().program_to_dc(&program).unwrap());
+        }
+
+        #[test]
+        fn dc_nested_if_else() {
+            let program = Program {
+                stmts: vec![Stmt::If {
+                    cond: var("x"),
+                    then: Box::new(Stmt::If {
+                        cond: var("y"),
+                        then: Box::new(Stmt::Expr(assign(var("z"), num("1")))),
+                        otherwise: None,
+                    }),
+                    otherwise: Some(Box::new(Stmt::Expr(num("2")))),
+                }],
+            };
+            assert_eq!(
+                "[2 p] lx 0 [sC [[] ly 0 [sC [1 sz]] sB !=B x]] sA !=A x",
+                DcGen::new().program_to_dc(&program).unwrap()
+            );
+        }
+
+        #[test]
+        fn dc_while() {
+            let program = Program {
+                stmts: vec![Stmt::While {
+                    cond: var("x"),
+                    body: Box::new(Stmt::Expr(assign(var("x"), binop(var("x"), '-', num("1"))))),
+                    span: Span::default(),
+                }],
+            };
+            assert_eq!(
+                "[2Q] sB [lx 0 =B [lx 1 - sx] x lA x] SA lA x LA sC",
+                DcGen::new().program_to_dc(&program).unwrap()
+            );
+        }
+
+        #[test]
+        fn dc_for_break_continue() {
+            let program = Program {
+                stmts: vec![Stmt::For {
+                    init: Some(num("1")),
+                    cond: None,
+                    step: Some(assign(var("i"), num("2"))),
+                    body: Box::new(Stmt::Block(vec![
+                        Stmt::If { cond: var("i"), then: Box::new(Stmt::Break), otherwise: None },
+                        Stmt::Continue,
+                    ])),
+                    span: Span::default(),
+                }],
+            };
+            assert_eq!(
+                "1 sA [2Q] sC [[[] li 0 [sA [3Q]] sE !=E x 1Q] x 2 si lB x] SB lB x LB sA",
+                DcGen::new().program_to_dc(&program).unwrap()
+            );
+        }
+
+        #[test]
+        fn dc_print() {
+            let items = vec![
+                PrintItem::Str(String::from("a[1] = ")),
+                PrintItem::Expr(index("a", num("1"))),
+                PrintItem::Str(String::from("\n")),
+            ];
+            assert_eq!("[a]P 9 A*1+P [1]P 9 A*3+P [ = ]P 1 ;A n AP", stmt_to_dc(&Stmt::Print(items)));
+            assert_eq!("", stmt_to_dc(&Stmt::Print(vec![PrintItem::Str(String::new())])));
+        }
+
+        #[test]
+        fn dc_print_after_ibase() {
+            let program = Program {
+                stmts: vec![
+                    Stmt::Expr(assign(special(Special::Ibase), num("16"))),
+                    Stmt::Print(vec![PrintItem::Str(String::from("[x]\n"))]),
+                ],
+            };
+            assert_eq!("16 i 9 A*1+P [x]P 9 A*3+P AP", DcGen::new().program_to_dc(&program).unwrap());
+        }
+
+        #[test]
+        fn dc_print_non_ascii() {
+            let items = vec![PrintItem::Str(String::from("caf\u{e9} \u{20ac}"))];
+            assert_eq!("[caf]P 5 A* A* A*8+ A*9+P [ ]P E A*8+ A*4+ A*4+ A*5+ A*8+ A*8+P", stmt_to_dc(&Stmt::Print(items)));
+        }
+
+        #[test]
+        fn dc_constants() {
+            let constants: Vec<String> = [0, 9, 10, 15, 16, 20, 91, 100, 255].iter().map(|&n| constant_to_dc(n)).collect();
+            assert_eq!(vec!["0", "9", "A", "F", "1 A*6+", "2 A*", "9 A*1+", "A A*", "2 A*5+ A*5+"], constants);
+        }
+
+        #[test]
+        fn dc_empty_program() {
+            assert_eq!("", DcGen::new().program_to_dc(&Program { stmts: vec![] }).unwrap());
+        }
+    }
+
+    mod functions {
+        use super::super::super::parser::{call, index, Function};
+        use super::*;
+        use std::rc::Rc;
+
+        /**
+         * Names ending in [] declare arrays.
+         */
+        fn param(name: &str) -> Param {
+            match name.strip_suffix("[]") {
+                Some(name) => Param::Array(String::from(name)),
+                None => Param::Var(String::from(name)),
+            }
+        }
+
+        fn define(name: &str, params: &[&str], autos: &[&str], body: Vec<Stmt>) -> Stmt {
+            Stmt::Define(Rc::new(Function {
+                name: String::from(name),
+                params: params.iter().map(|name| param(name)).collect(),
+                autos: autos.iter().map(|name| param(name)).collect(),
+                body,
+            }))
+        }
+
+        #[test]
+        fn dc_define_and_call() {
+            let mut gen = DcGen::new();
+            let function = define(
+                "f",
+                &["x", "y"],
+                &["z"],
+                vec![
+                    Stmt::Expr(assign(var("z"), binop(var("x"), '-', var("y")))),
+                    Stmt::Expr(var("z")),
+                    Stmt::Return(Some(var("z"))),
+                ],
+            );
+            assert_eq!(
+                "[Sy Sx 0 Sz [lx ly - sz lz p sA lz sC 1Q 0 sC] x lC Lx sA Ly sA Lz sA] sD",
+                gen.stmt_to_dc(&function).unwrap()
+            );
+            assert_eq!("5 2 lD x p", gen.to_dc(&call("f", vec![num("5"), num("2")])).unwrap());
+        }
+
+        #[test]
+        fn dc_return_from_loop() {
+            let function = define(
+                "g",
+                &[],
+                &[],
+                vec![Stmt::While { cond: num("1"), body: Box::new(Stmt::Return(None)), span: Default::default() }],
+            );
+            assert_eq!(
+                "[[[2Q] sD [1 0 =D [0 sF 1 sG 2Q] x lC x] SC lC x LC sA lG 0 [0 sG 2Q] sE !=E 0 sF] x lF] sH",
+                DcGen::new().stmt_to_dc(&function).unwrap()
+            );
+        }
+
+        #[test]
+        fn dc_call_err_arity() {
+            let mut gen = DcGen::new();
+            gen.stmt_to_dc(&define("f", &["x"], &[], vec![])).unwrap();
+            assert_eq!(
+                Err(ThbcError::Runtime {
+                    message: String::from("Function f takes 1 arguments, not 0"),
+                    span: Default::default(),
+                }),
+                gen.to_dc(&call("f", vec![]))
+            );
+        }
+
+        #[test]
+        fn dc_index() {
+            let mut gen = DcGen::new();
+            assert_eq!(Ok(String::from("2 ;A p")), gen.to_dc(&index("a", num("2"))));
+            assert_eq!(
+                Ok(String::from("5 li d 1+ d lB [d sB] sD <D sC :A")),
+                gen.stmt_to_dc(&Stmt::Expr(assign(index("a", var("i")), num("5"))))
+            );
+        }
+
+        #[test]
+        fn dc_pass_array() {
+            let mut gen = DcGen::new();
+            let function = define("f", &["x[]", "y"], &[], vec![Stmt::Return(Some(index("x", var("y"))))]);
+            assert_eq!(
+                concat!(
+                    "[Sy 0 SB lE SC 0 sF [lF;D lF:B lF1+ d sF lC >G] sG 0 lC >G ",
+                    "[ly ;B sH 1Q 0 sH] x lH LB sA LC sA Ly sA] sI"
+                ),
+                gen.stmt_to_dc(&function).unwrap()
+            );
+            assert_eq!(
+                Ok(String::from("2 lK sE 0 sF [lF;J lF:D lF1+ d sF lE >G] sG 0 lE >G lI x p")),
+                gen.to_dc(&call("f", vec![Expr::Array(String::from("b")), num("2")]))
+            );
+        }
+
+        #[test]
+        fn dc_call_err_array_arg() {
+            let mut gen = DcGen::new();
+            gen.stmt_to_dc(&define("f", &["x[]"], &[], vec![])).unwrap();
+            assert_eq!(
+                Err(ThbcError::Runtime {
+                    message: String::from("Argument 1 of f must be an array"),
+                    span: Default::default(),
+                }),
+                gen.to_dc(&call("f", vec![var("a")]))
+            );
+        }
+    }
+
+    mod names {
+        use super::*;
+
+        #[test]
+        fn dc_long_names_get_registers() {
+            let mut gen = DcGen::new();
+            assert_eq!(
+                Ok(String::from("lx 1 + sA")),
+                gen.stmt_to_dc(&Stmt::Expr(assign(var("total"), binop(var("x"), '+', num("1")))))
+            );
+            assert_eq!(Ok(String::from("lB sA")), gen.stmt_to_dc(&Stmt::Expr(assign(var("total"), var("count")))));
+            assert_eq!(Ok(String::from("lA lB * p")), gen.to_dc(&binop(var("total"), '*', var("count"))));
+        }
+
+        #[test]
+        fn dc_registers_past_ascii() {
+            let mut gen = DcGen::new();
+            for i in 0..REGISTER_POOL.len() {
+                assert!(gen.to_dc(&var(&format!("v{}", i))).is_ok());
+            }
+            assert_eq!(Ok(String::from("l\u{80} p")), gen.to_dc(&var("past")));
+            assert_eq!(Ok(String::from("l\u{81} p")), gen.to_dc(&var("later")));
+            assert_eq!(Ok(String::from("l\u{80} p")), gen.to_dc(&var("past")));
+        }
+
+        #[test]
+        fn dc_err_out_of_registers() {
+            let mut gen = DcGen::new();
+            for i in 0..REGISTER_POOL.len() + 0x80 {
+                assert!(gen.to_dc(&var(&format!("v{}", i))).is_ok());
+            }
+            assert_eq!(
+                Err(ThbcError::Backend {
+                    message: String::from("Too many names for the dc backend: overflow")
+                }),
+                gen.to_dc(&var("overflow"))
+            );
+        }
+    }
 }
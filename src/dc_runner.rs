@@ -0,0 +1,179 @@
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::thread::JoinHandle;
+
+/**
+ * thbc - Tar Heel Basic Calculator - DCRunner
+ *
+ * Author: Daniel Evora
+ * ONYEN: devora
+ *
+ * UNC Honor Pledge: I pledge I have received no unauthorized aid
+ * on this assignment. I further pledge not to distribute my solution
+ * to this code to anyone other than the course staff.
+ */
+
+/**
+ * A DcRunner owns one long-lived dc-compatible process. Every program is
+ * written to the same process so registers persist from one line of input
+ * to the next. The process's stdout is streamed straight to ours and each
+ * line it writes to stderr is reported as a thbc error, which is also
+ * remembered for thbc's exit status.
+ *
+ * Each program is followed by SYNC, which makes dc print MARKER once it is
+ * done with the program. The marker is kept out of our stdout and lets run
+ * wait until the program's output is all out, so it can't trail behind
+ * what thbc writes for the next line. dc's stderr has nothing like it, so
+ * an error dc reports may still show up a little after the output around
+ * it.
+ */
+pub struct DcRunner {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    forwarders: Vec<JoinHandle<()>>,
+    failed: Arc<AtomicBool>,
+    synced: Receiver<()>,
+}
+
+/**
+ * Prints the bytes 1, "thbc" and 1, using only single digits so that the
+ * input base can't change what is printed.
+ */
+const SYNC: &[u8] = b"1P [thbc]P 1P\n";
+const MARKER: &[u8] = b"\x01thbc\x01";
+
+impl DcRunner {
+    /**
+     * Spawns the executable at `path`, which is looked up on PATH when it
+     * has no directory component, just like a shell would.
+     */
+    pub fn spawn(path: &str) -> io::Result<DcRunner> {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let stdout = child.stdout.take().unwrap();
+        let stderr = child.stderr.take().unwrap();
+        let failed = Arc::new(AtomicBool::new(false));
+        let reported = Arc::clone(&failed);
+        let (sync, synced) = mpsc::channel();
+        let forwarders = vec![
+            thread::spawn(move || forward_stdout(stdout, sync)),
+            thread::spawn(move || {
+                // dc may quote a register name, which need not be UTF-8.
+                for line in BufReader::new(stderr).split(b'\n') {
+                    match line {
+                        Ok(message) => {
+                            reported.store(true, Ordering::SeqCst);
+                            eprintln!("thbc: {}", String::from_utf8_lossy(&message));
+                        }
+                        Err(_) => break,
+                    }
+                }
+            }),
+        ];
+
+        Ok(DcRunner {
+            stdin: child.stdin.take(),
+            child,
+            forwarders,
+            failed,
+            synced,
+        })
+    }
+
+    /**
+     * Sends one dc program to the process, terminated by a newline, and
+     * waits until its output has been forwarded. dc registers are named by
+     * bytes, and DcGen keeps every char of a program below U+0100 so that
+     * each is written as the byte of the same value.
+     */
+    pub fn run(&mut self, program: &str) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(program.len() + 1);
+        for c in program.chars() {
+            match u32::from(c) {
+                byte @ 0..=0xFF => bytes.push(byte as u8),
+                _ => {
+                    let message = format!("{:?} can't be sent to dc", c);
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+                }
+            }
+        }
+        bytes.push(b'\n');
+        bytes.extend_from_slice(SYNC);
+        let stdin = self.stdin.as_mut().unwrap();
+        stdin.write_all(&bytes)?;
+        stdin.flush()?;
+        // Once dc's stdout is closed there is no marker to wait for.
+        let _ = self.synced.recv();
+        Ok(())
+    }
+
+    /**
+     * Closing dc's stdin lets it finish any pending work and exit on its own.
+     * We then wait for it and for the output it produced to be forwarded,
+     * and tell whether it reported any errors.
+     */
+    pub fn close(&mut self) -> bool {
+        self.stdin.take();
+        let _ = self.child.wait();
+        for forwarder in self.forwarders.drain(..) {
+            let _ = forwarder.join();
+        }
+        self.failed.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for DcRunner {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/**
+ * Copies dc's stdout to ours, leaving out each MARKER and reporting it to
+ * `sync` once everything dc printed before it has been written and
+ * flushed. Bytes that may be the start of a marker are held back until
+ * the next read tells.
+ */
+fn forward_stdout(mut stdout: ChildStdout, sync: Sender<()>) {
+    let mut out = io::stdout();
+    let mut pending = Vec::new();
+    let mut chunk = [0; 4096];
+    loop {
+        let read = match stdout.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+        pending.extend_from_slice(&chunk[..read]);
+        // If our stdout goes away there is nowhere left to report to.
+        while let Some(at) = pending.windows(MARKER.len()).position(|bytes| bytes == MARKER) {
+            let _ = out.write_all(&pending[..at]).and_then(|_| out.flush());
+            pending.drain(..at + MARKER.len());
+            let _ = sync.send(());
+        }
+        let held = (1..MARKER.len()).rev().find(|&held| pending.ends_with(&MARKER[..held])).unwrap_or(0);
+        let _ = out.write_all(&pending[..pending.len() - held]).and_then(|_| out.flush());
+        pending.drain(..pending.len() - held);
+    }
+    let _ = out.write_all(&pending).and_then(|_| out.flush());
+}
+
+#[cfg(test)]
+mod spawn {
+    use super::*;
+
+    #[test]
+    fn spawn_err_missing_executable() {
+        assert!(DcRunner::spawn("thbc-no-such-dc").is_err());
+    }
+}
@@ -0,0 +1,307 @@
+use super::tokenizer::{Span, Token};
+use std::error;
+use std::fmt;
+
+/**
+ * thbc - Tar Heel Basic Calculator - Error
+ *
+ * Author: Daniel Evora
+ * ONYEN: devora
+ *
+ * UNC Honor Pledge: I pledge I have received no unauthorized aid
+ * on this assignment. I further pledge not to distribute my solution
+ * to this code to anyone other than the course staff.
+ */
+
+/**
+ * Everything that can go wrong while running a line of input. Every kind of
+ * error has a stable code, and all of them except backend errors carry the
+ * span of input they are about.
+ *
+ * The `expected` lists describe what the parser would have accepted instead,
+ * such as "an expression" or "')'".
+ */
+#[derive(Debug, PartialEq, Clone)]
+pub enum ThbcError {
+    Lex {
+        message: String,
+        span: Span,
+    },
+    UnexpectedToken {
+        found: Token,
+        expected: Vec<&'static str>,
+        span: Span,
+    },
+    UnexpectedEof {
+        expected: Vec<&'static str>,
+        span: Span,
+    },
+    UnbalancedParen {
+        span: Span,
+    },
+    InvalidAssignment {
+        target: String,
+        span: Span,
+    },
+    Runtime {
+        message: String,
+        span: Span,
+    },
+    Backend {
+        message: String,
+    },
+    OutsideLoop {
+        keyword: &'static str,
+        span: Span,
+    },
+    OutsideFunction {
+        keyword: &'static str,
+        span: Span,
+    },
+    TooDeep {
+        span: Span,
+    },
+}
+
+impl ThbcError {
+    /**
+     * The code identifying this kind of error. Codes are never reused, so
+     * scripts and tests can rely on them instead of on the wording.
+     */
+    pub fn code(&self) -> &'static str {
+        match self {
+            ThbcError::Lex { .. } => "E0001",
+            ThbcError::UnexpectedToken { .. } => "E0002",
+            ThbcError::UnexpectedEof { .. } => "E0003",
+            ThbcError::UnbalancedParen { .. } => "E0004",
+            ThbcError::InvalidAssignment { .. } => "E0005",
+            ThbcError::Runtime { .. } => "E0006",
+            ThbcError::Backend { .. } => "E0007",
+            ThbcError::OutsideLoop { .. } => "E0008",
+            ThbcError::OutsideFunction { .. } => "E0009",
+            ThbcError::TooDeep { .. } => "E0010",
+        }
+    }
+
+    /**
+     * The part of the input the error is about. A span on line 0 means the
+     * error has no particular position.
+     */
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ThbcError::Lex { span, .. }
+            | ThbcError::UnexpectedToken { span, .. }
+            | ThbcError::UnexpectedEof { span, .. }
+            | ThbcError::UnbalancedParen { span }
+            | ThbcError::InvalidAssignment { span, .. }
+            | ThbcError::Runtime { span, .. }
+            | ThbcError::OutsideLoop { span, .. }
+            | ThbcError::OutsideFunction { span, .. }
+            | ThbcError::TooDeep { span } => Some(*span).filter(|span| span.line > 0),
+            ThbcError::Backend { .. } => None,
+        }
+    }
+
+    /**
+     * Whether more input could make the error go away, because the input
+     * ended in the middle of something, like a block or a function.
+     */
+    pub fn is_incomplete(&self, source: &str) -> bool {
+        match self {
+            ThbcError::UnexpectedEof { .. } => true,
+            ThbcError::Lex { span, .. } => span.end == source.len(), //unterminated strings and comments run to the end
+            _ => false,
+        }
+    }
+
+    /**
+     * Formats the error for the user. When it has a position, that is given
+     * as line:column and followed by the offending line of `source` with a
+     * caret under the column, for example:
+     *
+     * 1:5: error[E0002]: Unexpected token '*', expected an expression
+     * 1 + * 2
+     *     ^
+     */
+    pub fn render(&self, source: &str) -> String {
+        match self.span() {
+            Some(span) => format!("{}:{}: {}", span.line, span.column, self.excerpt(source, span)),
+            None => self.headline(),
+        }
+    }
+
+    /**
+     * Formats the error like render, for `source` that starts on line
+     * `first_line` of `file`. The position is file:line:column with lines
+     * counted from the top of the file, and an error without one still
+     * names the file and the line `source` starts on.
+     */
+    pub fn render_in(&self, source: &str, file: &str, first_line: usize) -> String {
+        match self.span() {
+            Some(span) => {
+                let line = first_line + span.line - 1;
+                format!("{}:{}:{}: {}", file, line, span.column, self.excerpt(source, span))
+            }
+            None => format!("{}:{}: {}", file, first_line, self.headline()),
+        }
+    }
+
+    fn headline(&self) -> String {
+        format!("error[{}]: {}", self.code(), self)
+    }
+
+    fn excerpt(&self, source: &str, span: Span) -> String {
+        let mut output = self.headline();
+        if let Some(line) = source.lines().nth(span.line - 1) {
+            // tabs are kept so that the caret lines up however they are shown
+            let indent: String = line
+                .chars()
+                .take(span.column - 1)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            output.push_str(&format!("\n{}\n{}^", line, indent));
+        }
+        output
+    }
+}
+
+impl fmt::Display for ThbcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ThbcError::Lex { message, .. } => write!(f, "{}", message),
+            ThbcError::UnexpectedToken { found: Token::Newline, expected, .. } => {
+                write!(f, "Unexpected newline")?;
+                write_expected(f, expected)
+            }
+            ThbcError::UnexpectedToken { found, expected, .. } => {
+                write!(f, "Unexpected token {}", found)?;
+                write_expected(f, expected)
+            }
+            ThbcError::UnexpectedEof { expected, .. } => {
+                write!(f, "Unexpected end of input")?;
+                write_expected(f, expected)
+            }
+            ThbcError::UnbalancedParen { .. } => write!(f, "Unbalanced parenthesis"),
+            ThbcError::InvalidAssignment { target, .. } => write!(f, "Cannot assign to {}", target),
+            ThbcError::Runtime { message, .. } => write!(f, "{}", message),
+            ThbcError::Backend { message } => write!(f, "{}", message),
+            ThbcError::OutsideLoop { keyword, .. } => write!(f, "{} outside of a loop", keyword),
+            ThbcError::OutsideFunction { keyword, .. } => write!(f, "{} outside of a function", keyword),
+            ThbcError::TooDeep { .. } => write!(f, "Too deeply nested"),
+        }
+    }
+}
+
+fn write_expected(f: &mut fmt::Formatter, expected: &[&'static str]) -> fmt::Result {
+    if expected.is_empty() {
+        Ok(())
+    } else {
+        write!(f, ", expected {}", expected.join(" or "))
+    }
+}
+
+impl error::Error for ThbcError {}
+
+#[cfg(test)]
+mod render {
+    use super::*;
+
+    fn at(line: usize, column: usize) -> Span {
+        Span {
+            start: 0,
+            end: 0,
+            line,
+            column,
+        }
+    }
+
+    #[test]
+    fn render_points_at_column() {
+        let error = ThbcError::UnexpectedToken {
+            found: Token::Operator('*'),
+            expected: vec!["an expression"],
+            span: at(1, 5),
+        };
+        assert_eq!(
+            "1:5: error[E0002]: Unexpected token '*', expected an expression\n1 + * 2\n    ^",
+            error.render("1 + * 2\n")
+        );
+    }
+
+    #[test]
+    fn render_picks_line_and_keeps_tabs() {
+        let error = ThbcError::Runtime {
+            message: String::from("Divide by zero"),
+            span: at(2, 4),
+        };
+        assert_eq!(
+            "2:4: error[E0006]: Divide by zero\n\t1/0\n\t  ^",
+            error.render("x = 1\n\t1/0\n")
+        );
+    }
+
+    #[test]
+    fn render_without_position() {
+        let error = ThbcError::Backend {
+            message: String::from("Too many names"),
+        };
+        assert_eq!("error[E0007]: Too many names", error.render("1\n"));
+    }
+
+    #[test]
+    fn render_past_end_of_line() {
+        let error = ThbcError::UnexpectedEof {
+            expected: vec!["an expression"],
+            span: at(1, 4),
+        };
+        assert_eq!(
+            "1:4: error[E0003]: Unexpected end of input, expected an expression\n1 +\n   ^",
+            error.render("1 +\n")
+        );
+    }
+
+    #[test]
+    fn render_in_file() {
+        let error = ThbcError::Runtime {
+            message: String::from("Divide by zero"),
+            span: at(2, 3),
+        };
+        assert_eq!(
+            "lib.bc:11:3: error[E0006]: Divide by zero\n1/0\n  ^",
+            error.render_in("x = 1\n1/0\n", "lib.bc", 10)
+        );
+        let error = ThbcError::Backend {
+            message: String::from("Too many names"),
+        };
+        assert_eq!("lib.bc:10: error[E0007]: Too many names", error.render_in("1\n", "lib.bc", 10));
+    }
+
+    #[test]
+    fn incomplete_input() {
+        let eof = ThbcError::UnexpectedEof {
+            expected: vec!["'}'"],
+            span: at(1, 4),
+        };
+        assert!(eof.is_incomplete("{ 1\n"));
+        let lex = |start, end| ThbcError::Lex {
+            message: String::new(),
+            span: Span { start, end, line: 1, column: 1 },
+        };
+        assert!(lex(2, 7).is_incomplete("1 \"abc\n"));
+        assert!(!lex(2, 3).is_incomplete("1 @ 2\n"));
+        assert!(!ThbcError::UnbalancedParen { span: at(1, 1) }.is_incomplete("(\n"));
+    }
+
+    #[test]
+    fn display_lists_expected() {
+        let error = ThbcError::UnexpectedToken {
+            found: Token::Number(String::from("2")),
+            expected: vec!["an operator", "end of input"],
+            span: Span::default(),
+        };
+        assert_eq!(
+            "Unexpected token number 2, expected an operator or end of input",
+            error.to_string()
+        );
+    }
+}
@@ -0,0 +1,948 @@
+use super::error::ThbcError;
+use super::number::Number;
+use super::parser::{Builtin, Expr, Function, Param, PrintItem, Program, Special, Stmt};
+use super::tokenizer::{RelOp, Span};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/**
+ * thbc - Tar Heel Basic Calculator - Eval
+ *
+ * Author: Daniel Evora
+ * ONYEN: devora
+ *
+ * UNC Honor Pledge: I pledge I have received no unauthorized aid
+ * on this assignment. I further pledge not to distribute my solution
+ * to this code to anyone other than the course staff.
+ */
+
+/**
+ * The native backend walks the syntax tree directly instead of going through
+ * dc. It mirrors dc's behavior: variables start out as 0 and persist between
+ * statements, and results are truncated to the current `scale`, which also
+ * starts out as 0. Number literals are read in `ibase` when they are
+ * evaluated and results are printed in `obase`. Arrays are kept apart from
+ * variables and grow to fit the largest index stored into them.
+ */
+pub struct Evaluator {
+    variables: HashMap<String, Number>,
+    scale: usize,
+    ibase: u32,
+    obase: u32,
+    iteration_limit: Option<u64>,
+    arrays: HashMap<String, Vec<Number>>,
+    functions: HashMap<String, Rc<Function>>,
+    calls: Vec<Frame>,
+}
+
+/**
+ * What a running function hides with its parameters and autos.
+ */
+type Frame = Vec<Hidden>;
+
+/**
+ * A variable or array hidden by a parameter or auto, with its value from
+ * before the call or None if it had never been set.
+ */
+enum Hidden {
+    Var(String, Option<Number>),
+    Array(String, Option<Vec<Number>>),
+}
+
+/**
+ * The value a parameter or auto starts out with.
+ */
+enum Local {
+    Value(Number),
+    Array(Vec<Number>),
+}
+
+/**
+ * How a statement finished: normally, by a break or continue that the
+ * innermost loop around it has to act on, or by returning from a function.
+ */
+enum Flow {
+    Next,
+    Break,
+    Continue,
+    Return(Number),
+}
+
+/**
+ * The largest scale bc allows.
+ */
+const MAX_SCALE: i64 = i32::MAX as i64;
+
+/**
+ * dc wraps long numbers by ending each full line with a backslash.
+ */
+const LINE_LENGTH: usize = 68;
+
+/**
+ * How many function calls may be running at once. Deeper recursion is an
+ * error rather than a crash.
+ */
+const MAX_CALL_DEPTH: usize = 1000;
+
+/**
+ * The largest array index bc allows.
+ */
+const MAX_INDEX: i64 = 65535;
+
+impl Evaluator {
+    pub fn new() -> Evaluator {
+        Evaluator {
+            variables: HashMap::new(),
+            scale: 0,
+            ibase: 10,
+            obase: 10,
+            iteration_limit: None,
+            arrays: HashMap::new(),
+            functions: HashMap::new(),
+            calls: Vec::new(),
+        }
+    }
+
+    /**
+     * Renders a value the way dc prints it in the current `obase`, splitting
+     * numbers too long for one line with a trailing backslash on every line
+     * but the last.
+     */
+    pub fn format(&self, value: &Number) -> String {
+        let text = value.to_string_radix(self.obase);
+        let mut output = String::with_capacity(text.len());
+        for (i, c) in text.chars().enumerate() {
+            if i > 0 && i % LINE_LENGTH == 0 {
+                output.push_str("\\\n");
+            }
+            output.push(c);
+        }
+        output
+    }
+
+    /**
+     * Runs every statement of a program in order, stopping at the first
+     * error. Whatever the statements before it printed stays printed.
+     */
+    pub fn run(&mut self, program: &Program, out: &mut dyn Write) -> Result<(), ThbcError> {
+        for stmt in &program.stmts {
+            self.exec(stmt, out)?;
+        }
+        Ok(())
+    }
+
+    /**
+     * Executes a statement, writing the values it prints to `out` one per
+     * line. Assignments at the top level are silent just like in dc_gen.
+     */
+    pub fn exec(&mut self, stmt: &Stmt, out: &mut dyn Write) -> Result<(), ThbcError> {
+        self.exec_flow(stmt, out).map(|_| ())
+    }
+
+    /**
+     * Stops every loop after `limit` iterations with an error instead of
+     * letting it run forever. There is no limit unless one is set.
+     */
+    pub fn set_iteration_limit(&mut self, limit: Option<u64>) {
+        self.iteration_limit = limit;
+    }
+
+    /**
+     * Executes a statement, telling the loop around it whether a break or
+     * continue cut it short.
+     */
+    fn exec_flow(&mut self, stmt: &Stmt, out: &mut dyn Write) -> Result<Flow, ThbcError> {
+        match stmt {
+            Stmt::Expr(expr @ Expr::Assign { .. }) | Stmt::Expr(expr @ Expr::OpAssign { .. }) => {
+                self.eval_to(expr, out)?;
+            }
+            Stmt::Expr(expr) => {
+                let value = self.eval_to(expr, out)?;
+                writeln!(out, "{}", self.format(&value)).map_err(output)?;
+            }
+            Stmt::Print(items) => {
+                for item in items {
+                    match item {
+                        PrintItem::Str(text) => write!(out, "{}", text),
+                        PrintItem::Expr(expr) => {
+                            let value = self.eval_to(expr, out)?;
+                            write!(out, "{}", self.format(&value))
+                        }
+                    }
+                    .map_err(output)?;
+                }
+            }
+            Stmt::Block(stmts) => {
+                for stmt in stmts {
+                    match self.exec_flow(stmt, out)? {
+                        Flow::Next => {}
+                        flow => return Ok(flow),
+                    }
+                }
+            }
+            Stmt::If { cond, then, otherwise } => {
+                if !self.eval_to(cond, out)?.is_zero() {
+                    return self.exec_flow(then, out);
+                } else if let Some(otherwise) = otherwise {
+                    return self.exec_flow(otherwise, out);
+                }
+            }
+            Stmt::While { cond, body, span } => {
+                let mut iterations = 0;
+                while !self.eval_to(cond, out)?.is_zero() {
+                    self.count_iteration(&mut iterations, *span)?;
+                    match self.exec_flow(body, out)? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        _ => {}
+                    }
+                }
+            }
+            Stmt::For { init, cond, step, body, span } => {
+                if let Some(init) = init {
+                    self.eval_to(init, out)?;
+                }
+                let mut iterations = 0;
+                loop {
+                    if let Some(cond) = cond {
+                        if self.eval_to(cond, out)?.is_zero() {
+                            break;
+                        }
+                    }
+                    self.count_iteration(&mut iterations, *span)?;
+                    match self.exec_flow(body, out)? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        _ => {}
+                    }
+                    if let Some(step) = step {
+                        self.eval_to(step, out)?;
+                    }
+                }
+            }
+            Stmt::Break => return Ok(Flow::Break),
+            Stmt::Continue => return Ok(Flow::Continue),
+            Stmt::Define(function) => {
+                self.functions.insert(function.name.clone(), Rc::clone(function));
+            }
+            Stmt::Return(value) => {
+                let value = match value {
+                    Some(value) => self.eval_to(value, out)?,
+                    None => Number::zero(),
+                };
+                return Ok(Flow::Return(value));
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn count_iteration(&self, iterations: &mut u64, span: Span) -> Result<(), ThbcError> {
+        *iterations += 1;
+        match self.iteration_limit {
+            Some(limit) if *iterations > limit => Err(runtime(
+                format!("Loop ran for more than {} iterations", limit),
+                span,
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /**
+     * Evaluates an expression. Errors point at the operator of the node that
+     * failed. Whatever the functions it calls print is thrown away.
+     */
+    pub fn eval(&mut self, expr: &Expr) -> Result<Number, ThbcError> {
+        self.eval_to(expr, &mut io::sink())
+    }
+
+    /**
+     * Evaluates an expression, writing what the functions it calls print
+     * to `out`.
+     */
+    fn eval_to(&mut self, expr: &Expr, out: &mut dyn Write) -> Result<Number, ThbcError> {
+        match expr {
+            Expr::BinOp { lhs, op, rhs, span } => {
+                let lhs = self.eval_to(lhs, out)?;
+                let rhs = self.eval_to(rhs, out)?;
+                binop(&lhs, *op, &rhs, self.scale).map_err(|message| runtime(message, *span))
+            }
+            Expr::Neg(operand) => Ok(-&self.eval_to(operand, out)?),
+            Expr::Num(value) => Number::from_radix(value, self.ibase)
+                .map_err(|message| runtime(message, Span::default())),
+            Expr::Var(name) => Ok(self.variables.get(name).cloned().unwrap_or_else(Number::zero)),
+            Expr::Special(Special::Scale) => Ok(Number::from(self.scale as i64)),
+            Expr::Special(Special::Ibase) => Ok(Number::from(i64::from(self.ibase))),
+            Expr::Special(Special::Obase) => Ok(Number::from(i64::from(self.obase))),
+            Expr::Builtin(Builtin::Scale, arg) => Ok(Number::from(self.eval_to(arg, out)?.scale() as i64)),
+            Expr::Rel { lhs, op, rhs } => {
+                let lhs = self.eval_to(lhs, out)?;
+                let rhs = self.eval_to(rhs, out)?;
+                let holds = match op {
+                    RelOp::Lt => lhs < rhs,
+                    RelOp::Le => lhs <= rhs,
+                    RelOp::Gt => lhs > rhs,
+                    RelOp::Ge => lhs >= rhs,
+                    RelOp::Eq => lhs == rhs,
+                    RelOp::Ne => lhs != rhs,
+                };
+                Ok(Number::from(i64::from(holds)))
+            }
+            Expr::And(lhs, rhs) => {
+                let holds = !self.eval_to(lhs, out)?.is_zero() && !self.eval_to(rhs, out)?.is_zero();
+                Ok(Number::from(i64::from(holds)))
+            }
+            Expr::Or(lhs, rhs) => {
+                let holds = !self.eval_to(lhs, out)?.is_zero() || !self.eval_to(rhs, out)?.is_zero();
+                Ok(Number::from(i64::from(holds)))
+            }
+            Expr::Not(operand) => Ok(Number::from(i64::from(self.eval_to(operand, out)?.is_zero()))),
+            Expr::Assign { target, value, span } => {
+                let value = self.eval_to(value, out)?;
+                Ok(self.update(target, *span, out, |_| Ok(value))?.1)
+            }
+            Expr::OpAssign { target, op, value, span } => {
+                let value = self.eval_to(value, out)?;
+                let scale = self.scale;
+                Ok(self.update(target, *span, out, |old| binop(old, *op, &value, scale))?.1)
+            }
+            Expr::IncDec { target, op, post, span } => {
+                let (old, new) = self.update(target, *span, out, |old| binop(old, *op, &Number::from(1), 0))?;
+                Ok(if *post { old } else { new })
+            }
+            Expr::Index { name, index, span } => {
+                let index = self.subscript(name, index, *span, out)?;
+                let array = self.arrays.get(name);
+                Ok(array.and_then(|array| array.get(index)).cloned().unwrap_or_else(Number::zero))
+            }
+            Expr::Array(name) => unreachable!("the parser only allows whole arrays as arguments: {}", name),
+            Expr::Call { name, args, span } => {
+                let function = match self.functions.get(name) {
+                    Some(function) => Rc::clone(function),
+                    None => return Err(runtime(format!("Function {} is not defined", name), *span)),
+                };
+                if args.len() != function.params.len() {
+                    return Err(runtime(
+                        format!("Function {} takes {} arguments, not {}", name, function.params.len(), args.len()),
+                        *span,
+                    ));
+                }
+                let mut locals = Vec::with_capacity(args.len());
+                for (position, (param, arg)) in function.params.iter().zip(args).enumerate() {
+                    locals.push(match (param, arg) {
+                        (Param::Var(_), Expr::Array(_)) => {
+                            let message = format!("Argument {} of {} can't be an array", position + 1, name);
+                            return Err(runtime(message, *span));
+                        }
+                        (Param::Var(_), arg) => Local::Value(self.eval_to(arg, out)?),
+                        (Param::Array(_), Expr::Array(array)) => {
+                            Local::Array(self.arrays.get(array).cloned().unwrap_or_default())
+                        }
+                        (Param::Array(_), _) => {
+                            let message = format!("Argument {} of {} must be an array", position + 1, name);
+                            return Err(runtime(message, *span));
+                        }
+                    });
+                }
+                self.call(&function, locals, *span, out)
+            }
+        }
+    }
+
+    /**
+     * Replaces the value of an assignable expression with what `change`
+     * makes of it, returning both the old and the new value. An element's
+     * subscript is only evaluated once.
+     */
+    fn update<F>(&mut self, target: &Expr, span: Span, out: &mut dyn Write, change: F) -> Result<(Number, Number), ThbcError>
+    where
+        F: FnOnce(&Number) -> Result<Number, String>,
+    {
+        if let Expr::Index { name, index, span: at } = target {
+            let index = self.subscript(name, index, *at, out)?;
+            let array = self.arrays.entry(name.clone()).or_default();
+            if array.len() <= index {
+                array.resize(index + 1, Number::zero());
+            }
+            let new = change(&array[index]).map_err(|message| runtime(message, span))?;
+            return Ok((std::mem::replace(&mut array[index], new.clone()), new));
+        }
+        let old = self.eval_to(target, out)?;
+        let new = change(&old).map_err(|message| runtime(message, span))?;
+        self.store(target, new.clone()).map_err(|message| runtime(message, span))?;
+        Ok((old, new))
+    }
+
+    /**
+     * Evaluates the index of an element of the array `name`. Like in bc, the
+     * fraction is dropped and only indexes up to MAX_INDEX are allowed.
+     */
+    fn subscript(&mut self, name: &str, index: &Expr, span: Span, out: &mut dyn Write) -> Result<usize, ThbcError> {
+        match self.eval_to(index, out)?.to_i64() {
+            Some(index) if (0..=MAX_INDEX).contains(&index) => Ok(index as usize),
+            _ => Err(runtime(format!("Array {} subscript out of bounds", name), span)),
+        }
+    }
+
+    /**
+     * Runs a function with its parameters set to `args` and its autos to 0
+     * or an empty array. What they hide is kept in a frame on the call stack
+     * until the function returns, however it returns. Runtime errors in the
+     * function point at the call.
+     */
+    fn call(
+        &mut self,
+        function: &Function,
+        args: Vec<Local>,
+        span: Span,
+        out: &mut dyn Write,
+    ) -> Result<Number, ThbcError> {
+        if self.calls.len() >= MAX_CALL_DEPTH {
+            return Err(runtime(String::from("Function calls nested too deeply"), span));
+        }
+        let autos = function.autos.iter().map(|auto| match auto {
+            Param::Var(_) => (auto, Local::Value(Number::zero())),
+            Param::Array(_) => (auto, Local::Array(Vec::new())),
+        });
+        let mut frame = Vec::with_capacity(function.params.len() + function.autos.len());
+        for (param, local) in function.params.iter().zip(args).chain(autos) {
+            frame.push(match (param, local) {
+                (Param::Var(name), Local::Value(value)) => {
+                    Hidden::Var(name.clone(), self.variables.insert(name.clone(), value))
+                }
+                (Param::Array(name), Local::Array(array)) => {
+                    Hidden::Array(name.clone(), self.arrays.insert(name.clone(), array))
+                }
+                _ => unreachable!("arguments are checked against the parameters"),
+            });
+        }
+        self.calls.push(frame);
+        //the body may have come from another input, so errors in it point at the call
+        let result = self.exec_body(&function.body, out).map_err(|e| match e {
+            ThbcError::Runtime { message, .. } => runtime(message, span),
+            e => e,
+        });
+        for hidden in self.calls.pop().unwrap().into_iter().rev() {
+            match hidden {
+                Hidden::Var(name, Some(value)) => {
+                    self.variables.insert(name, value);
+                }
+                Hidden::Var(name, None) => {
+                    self.variables.remove(&name);
+                }
+                Hidden::Array(name, Some(array)) => {
+                    self.arrays.insert(name, array);
+                }
+                Hidden::Array(name, None) => {
+                    self.arrays.remove(&name);
+                }
+            }
+        }
+        result
+    }
+
+    fn exec_body(&mut self, body: &[Stmt], out: &mut dyn Write) -> Result<Number, ThbcError> {
+        for stmt in body {
+            if let Flow::Return(value) = self.exec_flow(stmt, out)? {
+                return Ok(value);
+            }
+        }
+        Ok(Number::zero())
+    }
+
+    /**
+     * Stores a value into an assignable Expr. The parser only ever builds
+     * assignments to variables and special variables.
+     */
+    fn store(&mut self, target: &Expr, value: Number) -> Result<(), String> {
+        match target {
+            Expr::Var(name) => {
+                self.variables.insert(name.clone(), value);
+            }
+            Expr::Special(Special::Scale) => {
+                self.scale = match value.to_i64() {
+                    Some(scale) if scale < 0 => {
+                        return Err(String::from("Scale must be a nonnegative number"))
+                    }
+                    Some(scale) if scale <= MAX_SCALE => scale as usize,
+                    _ => return Err(String::from("Scale too large")),
+                }
+            }
+            Expr::Special(Special::Ibase) => {
+                self.ibase = match value.to_i64() {
+                    Some(base) if (2..=16).contains(&base) => base as u32,
+                    _ => {
+                        return Err(String::from(
+                            "Input base must be a number between 2 and 16 (inclusive)",
+                        ))
+                    }
+                }
+            }
+            Expr::Special(Special::Obase) => {
+                self.obase = match value.to_i64() {
+                    Some(base) if (2..=i64::from(u32::MAX)).contains(&base) => base as u32,
+                    _ => return Err(String::from("Output base must be a number greater than 1")),
+                }
+            }
+            _ => unreachable!("not an assignable expression: {:?}", target),
+        }
+        Ok(())
+    }
+}
+
+impl Default for Evaluator {
+    fn default() -> Evaluator {
+        Evaluator::new()
+    }
+}
+
+fn runtime(message: String, span: Span) -> ThbcError {
+    ThbcError::Runtime { message, span }
+}
+
+fn output(e: io::Error) -> ThbcError {
+    ThbcError::Backend { message: format!("could not write output: {}", e) }
+}
+
+fn binop(lhs: &Number, op: char, rhs: &Number, scale: usize) -> Result<Number, String> {
+    match op {
+        '+' => Ok(lhs.add(rhs)),
+        '-' => Ok(lhs.sub(rhs)),
+        '*' => Ok(lhs.mul(rhs, scale)),
+        '/' => lhs.div(rhs, scale),
+        '%' => lhs.rem(rhs, scale),
+        '^' => lhs.pow(rhs, scale),
+        _ => Err(format!("Unknown operator: {}", op)),
+    }
+}
+
+#[cfg(test)]
+mod evaluator {
+    use super::super::parser::{assign, binop, builtin, call, index, neg, num, op_assign, post, pre, rel, special, var};
+    use super::*;
+
+    fn n(text: &str) -> Number {
+        text.parse().unwrap()
+    }
+
+    fn exec(e: &mut Evaluator, stmt: &Stmt) -> Result<String, ThbcError> {
+        let mut out = Vec::new();
+        e.exec(stmt, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn eval_arithmetic() {
+        let mut e = Evaluator::new();
+        assert_eq!(Ok(n("7")), e.eval(&binop(num("1"), '+', binop(num("2"), '*', num("3")))));
+        assert_eq!(Ok(n("-1")), e.eval(&binop(num("1"), '-', num("2"))));
+        assert_eq!(Ok(n("9")), e.eval(&binop(neg(num("3")), '^', num("2"))));
+    }
+
+    #[test]
+    fn eval_division_truncates() {
+        let mut e = Evaluator::new();
+        assert_eq!(Ok(n("3")), e.eval(&binop(num("7"), '/', num("2"))));
+        assert_eq!(Ok(n("-3")), e.eval(&binop(neg(num("7")), '/', num("2"))));
+        assert_eq!(Ok(n("0")), e.eval(&binop(num("2"), '^', neg(num("1")))));
+    }
+
+    #[test]
+    fn eval_modulo_uses_scale() {
+        let mut e = Evaluator::new();
+        assert_eq!(Ok(n("1")), e.eval(&binop(num("7"), '%', num("2"))));
+        assert_eq!(Ok(String::new()), exec(&mut e, &Stmt::Expr(assign(special(Special::Scale), num("2")))));
+        assert_eq!(Ok(n("0")), e.eval(&binop(num("7"), '%', num("2"))));
+        assert_eq!(".01", e.eval(&binop(num("1"), '%', num("3"))).unwrap().to_string());
+    }
+
+    #[test]
+    fn eval_err_divide_by_zero() {
+        let mut e = Evaluator::new();
+        assert_eq!(
+            Err(runtime(String::from("Divide by zero"), Span::default())),
+            e.eval(&binop(num("1"), '/', num("0")))
+        );
+    }
+
+    #[test]
+    fn eval_err_points_at_operator() {
+        use super::super::parser::Parser;
+        use super::super::tokenizer::Tokenizer;
+        let mut e = Evaluator::new();
+        let expr = Parser::parse(Tokenizer::new("2 * (1 / 0)")).unwrap();
+        assert_eq!(8, e.eval(&expr).unwrap_err().span().unwrap().column);
+        let expr = Parser::parse(Tokenizer::new("scale = -1")).unwrap();
+        assert_eq!(7, e.eval(&expr).unwrap_err().span().unwrap().column);
+    }
+
+    #[test]
+    fn eval_relations() {
+        use super::super::parser::rel;
+        let mut e = Evaluator::new();
+        assert_eq!(Ok(n("1")), e.eval(&rel(num("1"), RelOp::Lt, num("2"))));
+        assert_eq!(Ok(n("0")), e.eval(&rel(num("2"), RelOp::Lt, num("2"))));
+        assert_eq!(Ok(n("1")), e.eval(&rel(num("2.0"), RelOp::Eq, num("2"))));
+        assert_eq!(Ok(n("1")), e.eval(&rel(neg(num("3")), RelOp::Le, neg(num("0")))));
+        assert_eq!(Ok(n("0")), e.eval(&rel(num("1"), RelOp::Ne, num("1.000"))));
+        assert_eq!(Ok(n("1")), e.eval(&rel(num("10"), RelOp::Ge, num("9.99"))));
+    }
+
+    #[test]
+    fn eval_logic_short_circuits() {
+        use super::super::parser::{and, not, or};
+        let mut e = Evaluator::new();
+        assert_eq!(Ok(n("1")), e.eval(&and(num("2"), neg(num("1")))));
+        assert_eq!(Ok(n("0")), e.eval(&and(num("0"), assign(var("a"), num("5")))));
+        assert_eq!(Ok(n("1")), e.eval(&or(num("0.1"), assign(var("b"), num("5")))));
+        assert_eq!(Ok(n("0")), e.eval(&var("a")));
+        assert_eq!(Ok(n("0")), e.eval(&var("b")));
+        assert_eq!(Ok(n("1")), e.eval(&or(num("0"), assign(var("c"), num("5")))));
+        assert_eq!(Ok(n("5")), e.eval(&var("c")));
+        assert_eq!(Ok(n("1")), e.eval(&not(num("0"))));
+        assert_eq!(Ok(n("0")), e.eval(&not(num("0.001"))));
+    }
+
+    #[test]
+    fn eval_logic_skips_errors_on_rhs() {
+        use super::super::parser::and;
+        let mut e = Evaluator::new();
+        assert_eq!(Ok(n("0")), e.eval(&and(num("0"), binop(num("1"), '/', num("0")))));
+    }
+
+    #[test]
+    fn exec_variables_persist() {
+        let mut e = Evaluator::new();
+        assert_eq!(Ok(String::new()), exec(&mut e, &Stmt::Expr(assign(var("x"), num("12")))));
+        assert_eq!(Ok(String::from("13\n")), exec(&mut e, &Stmt::Expr(binop(var("x"), '+', num("1")))));
+        assert_eq!(Ok(String::from("0\n")), exec(&mut e, &Stmt::Expr(var("y"))));
+    }
+
+    #[test]
+    fn run_prints_each_statement() {
+        let mut e = Evaluator::new();
+        let program = Program {
+            stmts: vec![
+                Stmt::Expr(assign(var("x"), num("2"))),
+                Stmt::Block(vec![Stmt::Expr(var("x")), Stmt::Expr(binop(var("x"), '*', num("3")))]),
+                Stmt::Expr(binop(num("1"), '/', num("0"))),
+                Stmt::Expr(num("7")),
+            ],
+        };
+        let mut out = Vec::new();
+        let res = e.run(&program, &mut out);
+        assert_eq!(Err(runtime(String::from("Divide by zero"), Span::default())), res);
+        assert_eq!("2\n6\n", String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn exec_print() {
+        let mut e = Evaluator::new();
+        e.eval(&assign(special(Special::Obase), num("16"))).unwrap();
+        let items = vec![
+            PrintItem::Str(String::from("x = ")),
+            PrintItem::Expr(binop(num("255"), '+', num("1"))),
+            PrintItem::Str(String::from(", ")),
+            PrintItem::Expr(num("10")),
+        ];
+        assert_eq!(Ok(String::from("x = 100, A")), exec(&mut e, &Stmt::Print(items)));
+    }
+
+    #[test]
+    fn exec_if_else() {
+        let mut e = Evaluator::new();
+        let stmt = |cond| Stmt::If {
+            cond,
+            then: Box::new(Stmt::Expr(num("1"))),
+            otherwise: Some(Box::new(Stmt::Expr(num("2")))),
+        };
+        assert_eq!(Ok(String::from("1\n")), exec(&mut e, &stmt(num("0.5"))));
+        assert_eq!(Ok(String::from("2\n")), exec(&mut e, &stmt(rel(num("1"), RelOp::Gt, num("2")))));
+        let without_else = Stmt::If {
+            cond: num("0"),
+            then: Box::new(Stmt::Expr(num("1"))),
+            otherwise: None,
+        };
+        assert_eq!(Ok(String::new()), exec(&mut e, &without_else));
+    }
+
+    #[test]
+    fn exec_loops() {
+        let mut e = Evaluator::new();
+        let count_down = Stmt::While {
+            cond: var("x"),
+            body: Box::new(Stmt::Block(vec![
+                Stmt::Expr(assign(var("x"), binop(var("x"), '-', num("1")))),
+                Stmt::If { cond: rel(var("x"), RelOp::Eq, num("1")), then: Box::new(Stmt::Continue), otherwise: None },
+                Stmt::Expr(var("x")),
+            ])),
+            span: Span::default(),
+        };
+        e.exec(&Stmt::Expr(assign(var("x"), num("3"))), &mut Vec::new()).unwrap();
+        assert_eq!(Ok(String::from("2\n0\n")), exec(&mut e, &count_down));
+        let up_to_two = Stmt::For {
+            init: Some(assign(var("i"), num("0"))),
+            cond: None,
+            step: Some(assign(var("i"), binop(var("i"), '+', num("1")))),
+            body: Box::new(Stmt::Block(vec![
+                Stmt::If { cond: rel(var("i"), RelOp::Gt, num("2")), then: Box::new(Stmt::Break), otherwise: None },
+                Stmt::Expr(var("i")),
+            ])),
+            span: Span::default(),
+        };
+        assert_eq!(Ok(String::from("0\n1\n2\n")), exec(&mut e, &up_to_two));
+    }
+
+    #[test]
+    fn exec_iteration_limit() {
+        let mut e = Evaluator::new();
+        e.set_iteration_limit(Some(2));
+        let forever = Stmt::While { cond: num("1"), body: Box::new(Stmt::Expr(num("5"))), span: Span::default() };
+        let mut out = Vec::new();
+        assert_eq!(
+            Err(runtime(String::from("Loop ran for more than 2 iterations"), Span::default())),
+            e.exec(&forever, &mut out)
+        );
+        assert_eq!("5\n5\n", String::from_utf8(out).unwrap());
+    }
+
+    /**
+     * Names ending in [] declare arrays.
+     */
+    fn param(name: &str) -> Param {
+        match name.strip_suffix("[]") {
+            Some(name) => Param::Array(String::from(name)),
+            None => Param::Var(String::from(name)),
+        }
+    }
+
+    fn define(e: &mut Evaluator, name: &str, params: &[&str], autos: &[&str], body: Vec<Stmt>) {
+        let function = Function {
+            name: String::from(name),
+            params: params.iter().map(|name| param(name)).collect(),
+            autos: autos.iter().map(|name| param(name)).collect(),
+            body,
+        };
+        e.exec(&Stmt::Define(Rc::new(function)), &mut Vec::new()).unwrap();
+    }
+
+    #[test]
+    fn exec_call_hides_locals() {
+        let mut e = Evaluator::new();
+        define(
+            &mut e,
+            "f",
+            &["x"],
+            &["y"],
+            vec![
+                Stmt::Expr(assign(var("y"), binop(var("x"), '*', var("x")))),
+                Stmt::Expr(var("y")),
+                Stmt::Return(Some(binop(var("y"), '+', num("1")))),
+            ],
+        );
+        e.exec(&Stmt::Expr(assign(var("y"), num("7"))), &mut Vec::new()).unwrap();
+        let stmt = Stmt::Expr(binop(call("f", vec![num("3")]), '+', var("y")));
+        assert_eq!(Ok(String::from("9\n17\n")), exec(&mut e, &stmt));
+        assert_eq!(Ok(n("7")), e.eval(&var("y")));
+        assert_eq!(Ok(n("0")), e.eval(&var("x")));
+    }
+
+    #[test]
+    fn exec_recursion() {
+        let mut e = Evaluator::new();
+        define(
+            &mut e,
+            "fact",
+            &["n"],
+            &[],
+            vec![
+                Stmt::If { cond: rel(var("n"), RelOp::Lt, num("2")), then: Box::new(Stmt::Return(Some(num("1")))), otherwise: None },
+                Stmt::Return(Some(binop(var("n"), '*', call("fact", vec![binop(var("n"), '-', num("1"))])))),
+            ],
+        );
+        assert_eq!(Ok(n("3628800")), e.eval(&call("fact", vec![num("10")])));
+    }
+
+    #[test]
+    fn exec_return_from_loop() {
+        let mut e = Evaluator::new();
+        let forever = Stmt::While { cond: num("1"), body: Box::new(Stmt::Return(None)), span: Span::default() };
+        define(&mut e, "g", &[], &[], vec![forever, Stmt::Return(Some(num("5")))]);
+        define(&mut e, "h", &[], &[], vec![]);
+        assert_eq!(Ok(n("0")), e.eval(&call("g", vec![])));
+        assert_eq!(Ok(n("0")), e.eval(&call("h", vec![])));
+    }
+
+    #[test]
+    fn exec_call_errors() {
+        let mut e = Evaluator::new();
+        define(&mut e, "f", &["x"], &[], vec![]);
+        assert_eq!(
+            Err(runtime(String::from("Function g is not defined"), Span::default())),
+            e.eval(&call("g", vec![]))
+        );
+        assert_eq!(
+            Err(runtime(String::from("Function f takes 1 arguments, not 2"), Span::default())),
+            e.eval(&call("f", vec![num("1"), num("2")]))
+        );
+    }
+
+    #[test]
+    fn exec_arrays() {
+        let mut e = Evaluator::new();
+        assert_eq!(Ok(n("7")), e.eval(&assign(index("a", num("3")), num("7"))));
+        assert_eq!(Ok(n("7")), e.eval(&index("a", num("3.9"))));
+        assert_eq!(Ok(n("0")), e.eval(&index("a", num("100"))));
+        assert_eq!(Ok(n("0")), e.eval(&var("a")));
+        assert_eq!(
+            Err(runtime(String::from("Array a subscript out of bounds"), Span::default())),
+            e.eval(&index("a", num("65536")))
+        );
+        assert_eq!(
+            Err(runtime(String::from("Array a subscript out of bounds"), Span::default())),
+            e.eval(&assign(index("a", neg(num("1"))), num("1")))
+        );
+    }
+
+    #[test]
+    fn exec_arrays_pass_by_value() {
+        let mut e = Evaluator::new();
+        let body = vec![
+            Stmt::Expr(assign(index("x", num("0")), num("9"))),
+            Stmt::Expr(assign(index("b", num("0")), index("x", num("1")))),
+            Stmt::Return(Some(binop(index("x", num("0")), '+', index("b", num("0"))))),
+        ];
+        define(&mut e, "f", &["x[]"], &["b[]"], body);
+        e.eval(&assign(index("a", num("1")), num("4"))).unwrap();
+        e.eval(&assign(index("b", num("0")), num("5"))).unwrap();
+        assert_eq!(Ok(n("13")), e.eval(&call("f", vec![Expr::Array(String::from("a"))])));
+        assert_eq!(Ok(n("0")), e.eval(&index("a", num("0"))));
+        assert_eq!(Ok(n("5")), e.eval(&index("b", num("0"))));
+        assert_eq!(
+            Err(runtime(String::from("Argument 1 of f must be an array"), Span::default())),
+            e.eval(&call("f", vec![var("a")]))
+        );
+    }
+
+    #[test]
+    fn exec_op_assign() {
+        let mut e = Evaluator::new();
+        e.eval(&assign(var("x"), num("7"))).unwrap();
+        assert_eq!(Ok(String::new()), exec(&mut e, &Stmt::Expr(op_assign(var("x"), '%', num("4")))));
+        assert_eq!(Ok(n("27")), e.eval(&op_assign(var("x"), '^', num("3"))));
+        assert_eq!(Ok(n("30")), e.eval(&op_assign(index("a", num("1")), '+', op_assign(var("x"), '+', num("3")))));
+        assert_eq!(
+            Err(runtime(String::from("Divide by zero"), Span::default())),
+            e.eval(&op_assign(var("x"), '/', num("0")))
+        );
+        assert_eq!(Ok(n("30")), e.eval(&var("x")));
+    }
+
+    #[test]
+    fn exec_inc_dec() {
+        let mut e = Evaluator::new();
+        assert_eq!(Ok(String::from("0\n")), exec(&mut e, &Stmt::Expr(post(var("x"), '+'))));
+        assert_eq!(Ok(n("2")), e.eval(&pre('+', var("x"))));
+        assert_eq!(Ok(n("2")), e.eval(&post(var("x"), '-')));
+        assert_eq!(Ok(n("0")), e.eval(&pre('-', var("x"))));
+        assert_eq!(Ok(n("1")), e.eval(&pre('+', index("a", post(var("i"), '+')))));
+        assert_eq!(Ok(n("1")), e.eval(&index("a", num("0"))));
+        assert_eq!(Ok(n("1")), e.eval(&var("i")));
+        assert_eq!(
+            Err(runtime(String::from("Scale must be a nonnegative number"), Span::default())),
+            e.eval(&pre('-', special(Special::Scale)))
+        );
+    }
+
+    #[test]
+    fn exec_assignment_value() {
+        let mut e = Evaluator::new();
+        let stmt = Stmt::Expr(binop(assign(var("a"), num("2")), '*', var("a")));
+        assert_eq!(Ok(String::from("4\n")), exec(&mut e, &stmt));
+    }
+
+    #[test]
+    fn eval_exact_decimals() {
+        let mut e = Evaluator::new();
+        assert_eq!(
+            Ok(n("12345678901234567891")),
+            e.eval(&binop(num("12345678901234567890"), '+', num("1")))
+        );
+        assert_eq!(".3", e.eval(&binop(num("0.1"), '+', num("0.2"))).unwrap().to_string());
+        assert_eq!("2.2", e.eval(&binop(num("1.5"), '*', num("1.5"))).unwrap().to_string());
+    }
+
+    #[test]
+    fn exec_scale_controls_truncation() {
+        let mut e = Evaluator::new();
+        let scale = || special(Special::Scale);
+        assert_eq!(Ok(String::new()), exec(&mut e, &Stmt::Expr(assign(scale(), num("4")))));
+        assert_eq!(Ok(String::from("4\n")), exec(&mut e, &Stmt::Expr(scale())));
+        let third = e.eval(&binop(num("1"), '/', num("3"))).unwrap();
+        assert_eq!(".3333", third.to_string());
+        let product = e.eval(&binop(num("1.11111"), '*', num("3"))).unwrap();
+        assert_eq!("3.33333", product.to_string());
+        let power = e.eval(&binop(num("1.1"), '^', num("10"))).unwrap();
+        assert_eq!("2.5937", power.to_string());
+    }
+
+    #[test]
+    fn exec_err_negative_scale() {
+        let mut e = Evaluator::new();
+        assert_eq!(
+            Err(runtime(String::from("Scale must be a nonnegative number"), Span::default())),
+            exec(&mut e, &Stmt::Expr(assign(special(Special::Scale), neg(num("1")))))
+        );
+    }
+
+    #[test]
+    fn eval_scale_builtin() {
+        let mut e = Evaluator::new();
+        assert_eq!(Ok(n("3")), e.eval(&builtin(Builtin::Scale, num("1.250"))));
+        assert_eq!(Ok(n("0")), e.eval(&builtin(Builtin::Scale, num("12"))));
+    }
+
+    #[test]
+    fn exec_bases() {
+        let mut e = Evaluator::new();
+        let ibase = || special(Special::Ibase);
+        let obase = || special(Special::Obase);
+        assert_eq!(Ok(String::new()), exec(&mut e, &Stmt::Expr(assign(obase(), num("16")))));
+        let value = e.eval(&num("255")).unwrap();
+        assert_eq!("FF", e.format(&value));
+        assert_eq!(Ok(String::new()), exec(&mut e, &Stmt::Expr(assign(ibase(), num("2")))));
+        assert_eq!(Ok(n("5")), e.eval(&num("101")));
+        assert_eq!(Ok(n("12")), e.eval(&num("C")));
+        assert_eq!(Ok(String::new()), exec(&mut e, &Stmt::Expr(assign(ibase(), num("1010")))));
+        assert_eq!(Ok(n("10")), e.eval(&ibase()));
+    }
+
+    #[test]
+    fn exec_err_bad_bases() {
+        let mut e = Evaluator::new();
+        assert_eq!(
+            Err(runtime(String::from("Input base must be a number between 2 and 16 (inclusive)"), Span::default())),
+            exec(&mut e, &Stmt::Expr(assign(special(Special::Ibase), num("17"))))
+        );
+        assert_eq!(
+            Err(runtime(String::from("Output base must be a number greater than 1"), Span::default())),
+            exec(&mut e, &Stmt::Expr(assign(special(Special::Obase), num("1"))))
+        );
+    }
+
+    #[test]
+    fn format() {
+        let e = Evaluator::new();
+        assert_eq!("12", e.format(&n("12")));
+        assert_eq!(".5", e.format(&n("0.5")));
+        assert_eq!("-.5", e.format(&n("-0.5")));
+    }
+
+    #[test]
+    fn format_wraps_long_numbers() {
+        let e = Evaluator::new();
+        let digits = "1234567890".repeat(7);
+        let expected = format!("{}\\\n{}", &digits[..68], &digits[68..]);
+        assert_eq!(expected, e.format(&n(&digits)));
+    }
+}
@@ -19,11 +19,19 @@
  */
 extern crate structopt;
 
-const QUIT_STRING: &str = "quit\n";
 const EXIT_OK: i32 = 0;
 const EXIT_ERR: i32 = 1;
 
-use std::io;
+/**
+ * Recursive bc functions recurse in the native evaluator as well, so thbc
+ * runs on a thread with much more stack than the main thread gets.
+ */
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::thread;
 use structopt::StructOpt;
 #[derive(Debug, StructOpt)]
 #[structopt(name = "thbc", about = "Tar Heel Basic Calculator")]
@@ -32,76 +40,329 @@ struct Options {
     show_tokens: bool,
     #[structopt(short = "p", long = "show-parse")]
     show_parse: bool,
+    #[structopt(short = "d", long = "show-dc")]
+    show_dc: bool,
+    #[structopt(
+        long = "backend",
+        default_value = "native",
+        raw(possible_values = r#"&["native", "dc"]"#)
+    )]
+    backend: String,
+    #[structopt(long = "dc-path", default_value = "dc")]
+    dc_path: String,
+    /// Stops native loops that run for more than this many iterations
+    #[structopt(long = "max-iterations")]
+    max_iterations: Option<u64>,
+    /// Exits once the files have run instead of reading stdin
+    #[structopt(short = "q", long = "quiet")]
+    quiet: bool,
+    /// Script files to run in order before reading stdin
+    #[structopt(name = "FILE", parse(from_os_str))]
+    files: Vec<PathBuf>,
+}
+
+/**
+ * Where a piece of input came from, so that errors can say where it went
+ * wrong: the name of a script file and the line of it the piece starts on,
+ * or stdin, whose errors are placed within the piece itself.
+ */
+#[derive(Clone, Copy)]
+enum Origin<'a> {
+    Stdin,
+    File(&'a str, usize),
+}
+
+/**
+ * Gathers lines of input into pieces that can be run on their own. A piece
+ * is done once it parses or fails in a way more lines can't fix, so that a
+ * construct like a function definition may span several lines.
+ */
+#[derive(Default)]
+struct Pending {
+    text: String,
+    first_line: usize,
+    lines: usize,
+}
+
+impl Pending {
+    /**
+     * Adds a line, returning the piece it completes along with the number
+     * of the line the piece starts on.
+     */
+    fn push(&mut self, line: &str) -> Option<(String, usize)> {
+        self.lines += 1;
+        if self.text.is_empty() {
+            self.first_line = self.lines;
+        }
+        self.text.push_str(line);
+        match Parser::parse_program(Tokenizer::new(&self.text)) {
+            Err(error) if error.is_incomplete(&self.text) => None,
+            _ => Some(self.take()),
+        }
+    }
+
+    /**
+     * Whatever is left unfinished at the end of the input.
+     */
+    fn finish(&mut self) -> Option<(String, usize)> {
+        if self.text.is_empty() {
+            None
+        } else {
+            Some(self.take())
+        }
+    }
+
+    fn take(&mut self) -> (String, usize) {
+        (std::mem::take(&mut self.text), self.first_line)
+    }
+}
+
+/**
+ * Where statements are actually executed: either the built-in evaluator
+ * or an external dc process fed the code from dc_gen. The DcGen lives
+ * outside of the backend so --show-dc can print code even when it isn't run.
+ */
+enum Backend {
+    Native(Evaluator),
+    Dc(DcRunner),
 }
 
+pub mod error;
+use self::error::ThbcError;
 pub mod tokenizer;
-use self::tokenizer::Tokenizer;
+use self::tokenizer::{Keyword, Token, Tokenizer};
 pub mod parser;
 use self::parser::Parser;
 pub mod dc_gen;
+use self::dc_gen::DcGen;
+pub mod dc_runner;
+use self::dc_runner::DcRunner;
+pub mod eval;
+pub mod number;
+use self::eval::Evaluator;
 
 fn main() {
+    let thbc = thread::Builder::new().stack_size(STACK_SIZE).spawn(run);
+    match thbc.map(|thbc| thbc.join()) {
+        Ok(Ok(status)) => std::process::exit(status),
+        _ => std::process::exit(EXIT_ERR),
+    }
+}
+
+/**
+ * Runs the script files and then stdin, returning thbc's exit status.
+ */
+fn run() -> i32 {
     let options = Options::from_args();
-    loop {
-        eval(&read(), &options);
+    let mut backend = if options.backend == "dc" {
+        match DcRunner::spawn(&options.dc_path) {
+            Ok(dc) => Backend::Dc(dc),
+            Err(e) => {
+                let message = format!("could not start {}: {}", options.dc_path, e);
+                report("", Origin::Stdin, &ThbcError::Backend { message });
+                std::process::exit(EXIT_ERR);
+            }
+        }
+    } else {
+        let mut evaluator = Evaluator::new();
+        evaluator.set_iteration_limit(options.max_iterations);
+        Backend::Native(evaluator)
+    };
+    let mut gen = DcGen::new();
+    let mut failed = false;
+    for path in &options.files {
+        let name = path.to_string_lossy();
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => {
+                let message = format!("could not read {}: {}", name, e);
+                report("", Origin::Stdin, &ThbcError::Backend { message });
+                std::process::exit(EXIT_ERR);
+            }
+        };
+        let lines = text.split_inclusive('\n').map(String::from);
+        if run_lines(lines, Some(&name), &options, &mut backend, &mut gen, &mut failed) {
+            return status(backend, failed, false);
+        }
     }
+    if options.quiet {
+        return status(backend, failed, false);
+    }
+    run_lines(std::iter::from_fn(read), None, &options, &mut backend, &mut gen, &mut failed);
+    status(backend, failed, io::stdin().is_terminal())
 }
 
-fn eval(input: &str, options: &Options) {
+/**
+ * Runs the pieces that `lines` of a file, or of stdin when there is no
+ * file, make up, noting in `failed` when one of them fails. Like in bc, a
+ * quit anywhere ends thbc as soon as it is read, so the piece it is in
+ * never runs. Returns whether a quit was read.
+ */
+fn run_lines<I>(
+    lines: I,
+    file: Option<&str>,
+    options: &Options,
+    backend: &mut Backend,
+    gen: &mut DcGen,
+    failed: &mut bool,
+) -> bool
+where
+    I: Iterator<Item = String>,
+{
+    let mut run = |(input, first_line): (String, usize)| {
+        if quits(&input) {
+            return false;
+        }
+        let origin = match file {
+            Some(file) => Origin::File(file, first_line),
+            None => Origin::Stdin,
+        };
+        if !eval(&input, origin, options, backend, gen) {
+            *failed = true;
+        }
+        true
+    };
+    let mut pending = Pending::default();
+    for line in lines {
+        if let Some(piece) = pending.push(&line) {
+            if !run(piece) {
+                return true;
+            }
+        }
+    }
+    pending.finish().is_some_and(|piece| !run(piece))
+}
+
+/**
+ * Whether `input` holds the quit keyword anywhere but in strings and
+ * comments.
+ */
+fn quits(input: &str) -> bool {
+    Tokenizer::new(input).any(|item| matches!(item, Ok((Token::Keyword(Keyword::Quit), _))))
+}
+
+/**
+ * The exit status is an error when anything failed, unless thbc was used
+ * interactively, where errors are just part of the session. Errors dc
+ * reports count too, so it is given the chance to finish first.
+ */
+fn status(mut backend: Backend, mut failed: bool, interactive: bool) -> i32 {
+    if let Backend::Dc(dc) = &mut backend {
+        failed |= dc.close();
+    }
+    if failed && !interactive {
+        EXIT_ERR
+    } else {
+        EXIT_OK
+    }
+}
+
+/**
+ * Runs a piece of input, showing its tokens, parse tree and dc code first
+ * when asked to. Returns whether it ran without errors.
+ */
+fn eval(input: &str, origin: Origin, options: &Options, backend: &mut Backend, gen: &mut DcGen) -> bool {
     if options.show_tokens {
-        eval_show_tokens(input);
+        eval_show_tokens(input, origin);
     }
 
     if options.show_parse {
-        eval_show_parse(input);
+        eval_show_parse(input, origin);
     }
 
-    eval_target(input);
+    if options.show_dc {
+        eval_show_dc(input, origin, gen);
+    }
+
+    eval_target(input, origin, backend, gen)
 }
 
-fn eval_show_tokens(input: &str) {
+fn eval_show_tokens(input: &str, origin: Origin) {
     println!("== Tokens ==");
-    let mut tokens = Tokenizer::new(input);
-    while let Some(token) = tokens.next() {
-        println!("{:?}", token);
+    for item in Tokenizer::new(input) {
+        match item {
+            Ok((token, _)) => println!("{:?}", token),
+            Err(error) => report(input, origin, &error),
+        }
     }
-    print!("\n");
+    println!();
 }
 
-fn eval_show_parse(input: &str) {
+fn eval_show_parse(input: &str, origin: Origin) {
     println!("== Parse Tree ==");
-    match Parser::parse(Tokenizer::new(input)) {
-        Ok(statement) => {
-            println!("{:?}", statement);
+    match Parser::parse_program(Tokenizer::new(input)) {
+        Ok(program) => {
+            println!("{:?}", program);
         }
-        Err(msg) => eprintln!("thbc: {}", msg),
+        Err(error) => report(input, origin, &error),
     }
-    print!("\n");
+    println!();
 }
 
-fn eval_target(input: &str) {
-    match Parser::parse(Tokenizer::new(input)) {
-        Ok(statement) => {
-            println!("{}", dc_gen::to_dc(&statement));
-        }
-        Err(msg) => eprintln!("thbc: {}", msg),
+fn eval_show_dc(input: &str, origin: Origin, gen: &mut DcGen) {
+    println!("== DC ==");
+    match Parser::parse_program(Tokenizer::new(input)) {
+        Ok(program) => match gen.program_to_dc(&program) {
+            Ok(program) => {
+                println!("{}", program);
+            }
+            Err(error) => report(input, origin, &error),
+        },
+        Err(error) => report(input, origin, &error),
     }
+    println!();
 }
 
 /**
- * Read input from the user. We'll handle the case of quitting
- * via the string "quit" and exit the program from here.
+ * Runs a piece of input on the backend. What dc makes of the code it is
+ * sent only shows once dc gets to it, so success here means the code was
+ * sent.
  */
-fn read() -> String {
-    match read_line() {
-        Ok(line) => {
-            if line == QUIT_STRING {
-                // Exit the process with an Ok exit code.
-                std::process::exit(EXIT_OK);
-            } else {
-                line
+fn eval_target(input: &str, origin: Origin, backend: &mut Backend, gen: &mut DcGen) -> bool {
+    let result = Parser::parse_program(Tokenizer::new(input)).and_then(|program| match backend {
+        Backend::Native(evaluator) => evaluator.run(&program, &mut io::stdout()),
+        Backend::Dc(dc) => {
+            let code = gen.program_to_dc(&program)?;
+            if code.is_empty() {
+                return Ok(());
             }
+            if let Err(e) = dc.run(&code) {
+                let message = format!("lost connection to dc: {}", e);
+                report(input, origin, &ThbcError::Backend { message });
+                std::process::exit(EXIT_ERR);
+            }
+            Ok(())
+        }
+    });
+    match result {
+        Ok(()) => true,
+        Err(error) => {
+            report(input, origin, &error);
+            false
         }
+    }
+}
+
+/**
+ * Prints an error about `input` to stderr, pointing at where it went wrong.
+ * Errors in script files also name the file and count lines from its top.
+ */
+fn report(input: &str, origin: Origin, error: &ThbcError) {
+    match origin {
+        Origin::Stdin => eprintln!("thbc: {}", error.render(input)),
+        Origin::File(file, first_line) => eprintln!("thbc: {}", error.render_in(input, file, first_line)),
+    }
+}
+
+/**
+ * Read a line of input from the user, newline included. The end of the
+ * input is None, so that run can shut dc down cleanly before the process
+ * exits.
+ */
+fn read() -> Option<String> {
+    match read_line() {
+        Ok(line) if line.is_empty() => None,
+        Ok(line) => Some(line),
         Err(message) => {
             eprintln!("Err: {}", message);
             std::process::exit(EXIT_ERR);
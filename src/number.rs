@@ -0,0 +1,616 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Neg;
+use std::str::FromStr;
+
+/**
+ * thbc - Tar Heel Basic Calculator - Number
+ *
+ * Author: Daniel Evora
+ * ONYEN: devora
+ *
+ * UNC Honor Pledge: I pledge I have received no unauthorized aid
+ * on this assignment. I further pledge not to distribute my solution
+ * to this code to anyone other than the course staff.
+ */
+
+/**
+ * An arbitrary-precision decimal number following bc's model: a signed
+ * integer magnitude together with a scale, the count of digits after the
+ * decimal point. The magnitude's digits are stored least significant first
+ * with no leading zeros, so zero has no digits at all.
+ *
+ * Operations whose exact result could have too many fractional digits take
+ * the current `scale` and truncate towards zero, like bc and dc do.
+ */
+#[derive(Clone, Debug)]
+pub struct Number {
+    negative: bool,
+    digits: Vec<u8>,
+    scale: usize,
+}
+
+impl Number {
+    pub fn zero() -> Number {
+        Number {
+            negative: false,
+            digits: Vec::new(),
+            scale: 0,
+        }
+    }
+
+    pub fn scale(&self) -> usize {
+        self.scale
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn add(&self, other: &Number) -> Number {
+        let scale = self.scale.max(other.scale);
+        let lhs = shift(&self.digits, scale - self.scale);
+        let rhs = shift(&other.digits, scale - other.scale);
+        if self.negative == other.negative {
+            Number::new(self.negative, add_digits(&lhs, &rhs), scale)
+        } else {
+            match cmp_digits(&lhs, &rhs) {
+                Ordering::Less => Number::new(other.negative, sub_digits(&rhs, &lhs), scale),
+                _ => Number::new(self.negative, sub_digits(&lhs, &rhs), scale),
+            }
+        }
+    }
+
+    pub fn sub(&self, other: &Number) -> Number {
+        self.add(&-other)
+    }
+
+    /**
+     * The product keeps all of its fractional digits up to the larger of
+     * `scale` and the operands' scales.
+     */
+    pub fn mul(&self, other: &Number, scale: usize) -> Number {
+        let full = self.scale + other.scale;
+        let product = Number::new(
+            self.negative != other.negative,
+            mul_digits(&self.digits, &other.digits),
+            full,
+        );
+        product.truncate(full.min(scale.max(self.scale).max(other.scale)))
+    }
+
+    /**
+     * The quotient always has exactly `scale` fractional digits.
+     */
+    pub fn div(&self, other: &Number, scale: usize) -> Result<Number, String> {
+        if other.is_zero() {
+            return Err(String::from("Divide by zero"));
+        }
+        let numerator = shift(&self.digits, scale + other.scale);
+        let denominator = shift(&other.digits, self.scale);
+        let (quotient, _) = divmod_digits(&numerator, &denominator);
+        Ok(Number::new(self.negative != other.negative, quotient, scale))
+    }
+
+    /**
+     * The remainder the way bc defines it: `self - (self / other) * other`
+     * where the quotient has `scale` fractional digits. The result has the
+     * larger of `scale` plus the scale of `other` and the scale of `self`,
+     * so with a scale of 0 and integer operands this is the integer
+     * remainder, which takes the sign of `self`.
+     */
+    pub fn rem(&self, other: &Number, scale: usize) -> Result<Number, String> {
+        let quotient = self.div(other, scale)?;
+        let rscale = self.scale.max(other.scale + scale);
+        Ok(self.sub(&quotient.mul(other, rscale)))
+    }
+
+    /**
+     * Raises to an integer power. Any fractional part of the exponent is
+     * ignored. A negative exponent divides one by the positive power.
+     */
+    pub fn pow(&self, exponent: &Number, scale: usize) -> Result<Number, String> {
+        let exponent = match exponent.to_i64() {
+            Some(exponent) if exponent.unsigned_abs() <= u64::from(u32::MAX) => exponent,
+            _ => return Err(String::from("Exponent too large")),
+        };
+        let mut result = Number::from(1);
+        let mut base = self.clone();
+        let mut remaining = exponent.unsigned_abs();
+        while remaining > 0 {
+            if remaining & 1 == 1 {
+                result = result.mul(&base, result.scale + base.scale);
+            }
+            remaining >>= 1;
+            if remaining > 0 {
+                base = base.mul(&base, base.scale * 2);
+            }
+        }
+        if exponent < 0 {
+            Number::from(1).div(&result, scale)
+        } else {
+            let exponent = exponent as usize;
+            Ok(result.truncate((self.scale * exponent).min(scale.max(self.scale))))
+        }
+    }
+
+    /**
+     * Parses literal text whose digits, `0-9` and `A-F`, are in the given
+     * base. Like dc, digits are not checked against the base, so `F` is
+     * always fifteen. A fractional part is converted with as many decimal
+     * digits of scale as digits were written.
+     */
+    pub fn from_radix(text: &str, base: u32) -> Result<Number, String> {
+        let (whole, fraction) = match text.find('.') {
+            Some(point) => (&text[..point], &text[point + 1..]),
+            None => (text, ""),
+        };
+        let base = Number::from(i64::from(base));
+        let mut value = Number::zero();
+        for c in whole.chars() {
+            value = value.mul(&base, 0).add(&Number::from(radix_digit(c, text)?));
+        }
+        if !fraction.is_empty() {
+            let mut numerator = Number::zero();
+            let mut denominator = Number::from(1);
+            for c in fraction.chars() {
+                numerator = numerator.mul(&base, 0).add(&Number::from(radix_digit(c, text)?));
+                denominator = denominator.mul(&base, 0);
+            }
+            value = value.add(&numerator.div(&denominator, fraction.len())?);
+        }
+        Ok(value)
+    }
+
+    /**
+     * Formats the number in the given output base the way bc does. Bases
+     * up to 16 use the digits `0-9A-F`. Larger bases print every digit as a
+     * zero-padded decimal number set off by a space. The fractional part
+     * gets enough digits in the new base to be at least as precise as the
+     * scale.
+     */
+    pub fn to_string_radix(&self, base: u32) -> String {
+        if base == 10 || self.is_zero() {
+            return self.to_string();
+        }
+        let width = (base - 1).to_string().len();
+        let digit = |value: u32, spaced: bool| {
+            if base <= 16 {
+                std::char::from_digit(value, 16).unwrap().to_ascii_uppercase().to_string()
+            } else if spaced {
+                format!(" {:0width$}", value, width = width)
+            } else {
+                format!("{:0width$}", value, width = width)
+            }
+        };
+
+        let mut text = String::new();
+        if self.negative {
+            text.push('-');
+        }
+        let magnitude = if self.negative { -self } else { self.clone() };
+        let mut whole = magnitude.truncate(0).digits;
+        let mut whole_digits = Vec::new();
+        while !whole.is_empty() {
+            let (quotient, remainder) = divmod_small(&whole, base);
+            whole_digits.push(remainder);
+            whole = quotient;
+        }
+        for value in whole_digits.iter().rev() {
+            text.push_str(&digit(*value, true));
+        }
+
+        if self.scale > 0 {
+            text.push('.');
+            let base_number = Number::from(i64::from(base));
+            let mut fraction = magnitude.sub(&magnitude.truncate(0));
+            let mut place = Number::from(1);
+            let mut spaced = false;
+            while place.digits.len() <= self.scale {
+                fraction = fraction.mul(&base_number, self.scale);
+                let value = fraction.to_i64().unwrap();
+                fraction = fraction.sub(&Number::from(value));
+                text.push_str(&digit(value as u32, spaced));
+                spaced = true;
+                place = place.mul(&base_number, 0);
+            }
+        }
+        text
+    }
+
+    /**
+     * The integer part of the number, if it fits in an i64.
+     */
+    pub fn to_i64(&self) -> Option<i64> {
+        let mut value: i64 = 0;
+        for digit in self.digits.iter().skip(self.scale).rev() {
+            value = value.checked_mul(10)?.checked_add(i64::from(*digit))?;
+        }
+        Some(if self.negative { -value } else { value })
+    }
+
+    /**
+     * Drops fractional digits beyond `scale`. A number with fewer
+     * fractional digits is returned unchanged.
+     */
+    pub fn truncate(&self, scale: usize) -> Number {
+        if scale >= self.scale {
+            return self.clone();
+        }
+        let drop = self.scale - scale;
+        let digits = if drop >= self.digits.len() {
+            Vec::new()
+        } else {
+            self.digits[drop..].to_vec()
+        };
+        Number::new(self.negative, digits, scale)
+    }
+
+    /**
+     * Builds a normalized Number, trimming leading zeros and making sure
+     * zero is never negative.
+     */
+    fn new(negative: bool, mut digits: Vec<u8>, scale: usize) -> Number {
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        Number {
+            negative: negative && !digits.is_empty(),
+            digits,
+            scale,
+        }
+    }
+}
+
+impl From<i64> for Number {
+    fn from(value: i64) -> Number {
+        let mut digits = Vec::new();
+        let mut magnitude = value.unsigned_abs();
+        while magnitude > 0 {
+            digits.push((magnitude % 10) as u8);
+            magnitude /= 10;
+        }
+        Number::new(value < 0, digits, 0)
+    }
+}
+
+/**
+ * Parses decimal literal text such as `12`, `0.50` or `-3.25`. Every digit
+ * written after the decimal point counts towards the scale, even zeros.
+ */
+impl FromStr for Number {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Number, String> {
+        let (negative, unsigned) = if let Some(rest) = text.strip_prefix('-') {
+            (true, rest)
+        } else {
+            (false, text)
+        };
+        let mut digits = Vec::new();
+        let mut scale = 0;
+        let mut seen_point = false;
+        for c in unsigned.chars() {
+            match c {
+                '0'..='9' => {
+                    digits.push(c as u8 - b'0');
+                    if seen_point {
+                        scale += 1;
+                    }
+                }
+                '.' if !seen_point => seen_point = true,
+                _ => return Err(format!("Invalid number: {}", text)),
+            }
+        }
+        if digits.is_empty() {
+            return Err(format!("Invalid number: {}", text));
+        }
+        digits.reverse();
+        Ok(Number::new(negative, digits, scale))
+    }
+}
+
+/**
+ * Formats the number the way bc and dc print it: fractions have no leading
+ * zero (`.5`, `-.5`) and every digit of the scale is shown (`1.50`), except
+ * that zero is always just `0`.
+ */
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+        let mut text = String::new();
+        if self.negative {
+            text.push('-');
+        }
+        for digit in self.digits[self.scale.min(self.digits.len())..].iter().rev() {
+            text.push((b'0' + digit) as char);
+        }
+        if self.scale > 0 {
+            text.push('.');
+            for place in (0..self.scale).rev() {
+                let digit = self.digits.get(place).cloned().unwrap_or(0);
+                text.push((b'0' + digit) as char);
+            }
+        }
+        f.write_str(&text)
+    }
+}
+
+impl Neg for &Number {
+    type Output = Number;
+
+    fn neg(self) -> Number {
+        Number::new(!self.negative, self.digits.clone(), self.scale)
+    }
+}
+
+/**
+ * Numbers compare by value, so `1.50` equals `1.5`.
+ */
+impl PartialEq for Number {
+    fn eq(&self, other: &Number) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Number {}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Number) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Number {
+    fn cmp(&self, other: &Number) -> Ordering {
+        let difference = self.sub(other);
+        if difference.is_zero() {
+            Ordering::Equal
+        } else if difference.negative {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        }
+    }
+}
+
+fn radix_digit(c: char, text: &str) -> Result<i64, String> {
+    match c.to_digit(16) {
+        Some(value) if !c.is_ascii_lowercase() => Ok(i64::from(value)),
+        _ => Err(format!("Invalid number: {}", text)),
+    }
+}
+
+/* Helpers over magnitudes stored least significant digit first */
+
+/**
+ * Divides a magnitude by a small number, returning the quotient and the
+ * remainder.
+ */
+fn divmod_small(digits: &[u8], divisor: u32) -> (Vec<u8>, u32) {
+    let mut quotient = vec![0; digits.len()];
+    let mut remainder: u64 = 0;
+    for (place, digit) in digits.iter().enumerate().rev() {
+        let current = remainder * 10 + u64::from(*digit);
+        quotient[place] = (current / u64::from(divisor)) as u8;
+        remainder = current % u64::from(divisor);
+    }
+    while quotient.last() == Some(&0) {
+        quotient.pop();
+    }
+    (quotient, remainder as u32)
+}
+
+fn shift(digits: &[u8], places: usize) -> Vec<u8> {
+    if digits.is_empty() {
+        return Vec::new();
+    }
+    let mut shifted = vec![0; places];
+    shifted.extend_from_slice(digits);
+    shifted
+}
+
+fn cmp_digits(lhs: &[u8], rhs: &[u8]) -> Ordering {
+    lhs.len()
+        .cmp(&rhs.len())
+        .then_with(|| lhs.iter().rev().cmp(rhs.iter().rev()))
+}
+
+fn add_digits(lhs: &[u8], rhs: &[u8]) -> Vec<u8> {
+    let mut sum = Vec::with_capacity(lhs.len().max(rhs.len()) + 1);
+    let mut carry = 0;
+    for i in 0..lhs.len().max(rhs.len()) {
+        let total = lhs.get(i).unwrap_or(&0) + rhs.get(i).unwrap_or(&0) + carry;
+        sum.push(total % 10);
+        carry = total / 10;
+    }
+    if carry > 0 {
+        sum.push(carry);
+    }
+    sum
+}
+
+/**
+ * Subtracts rhs from lhs, which must not be the smaller of the two.
+ */
+fn sub_digits(lhs: &[u8], rhs: &[u8]) -> Vec<u8> {
+    let mut difference = Vec::with_capacity(lhs.len());
+    let mut borrow = 0;
+    for (i, digit) in lhs.iter().enumerate() {
+        let subtrahend = rhs.get(i).unwrap_or(&0) + borrow;
+        if *digit >= subtrahend {
+            difference.push(digit - subtrahend);
+            borrow = 0;
+        } else {
+            difference.push(digit + 10 - subtrahend);
+            borrow = 1;
+        }
+    }
+    while difference.last() == Some(&0) {
+        difference.pop();
+    }
+    difference
+}
+
+fn mul_digits(lhs: &[u8], rhs: &[u8]) -> Vec<u8> {
+    if lhs.is_empty() || rhs.is_empty() {
+        return Vec::new();
+    }
+    let mut product = vec![0u32; lhs.len() + rhs.len()];
+    for (i, a) in lhs.iter().enumerate() {
+        for (j, b) in rhs.iter().enumerate() {
+            product[i + j] += u32::from(*a) * u32::from(*b);
+        }
+        // Carry as we go so the accumulators never overflow.
+        let mut carry = 0;
+        for place in product.iter_mut().skip(i) {
+            let total = *place + carry;
+            *place = total % 10;
+            carry = total / 10;
+        }
+    }
+    let mut digits: Vec<u8> = product.into_iter().map(|d| d as u8).collect();
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+    digits
+}
+
+/**
+ * Schoolbook long division returning the quotient and the remainder.
+ */
+fn divmod_digits(numerator: &[u8], denominator: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let mut quotient = vec![0; numerator.len()];
+    let mut remainder: Vec<u8> = Vec::new();
+    for (place, digit) in numerator.iter().enumerate().rev() {
+        remainder.insert(0, *digit);
+        while remainder.last() == Some(&0) {
+            remainder.pop();
+        }
+        let mut count = 0;
+        while cmp_digits(&remainder, denominator) != Ordering::Less {
+            remainder = sub_digits(&remainder, denominator);
+            count += 1;
+        }
+        quotient[place] = count;
+    }
+    while quotient.last() == Some(&0) {
+        quotient.pop();
+    }
+    (quotient, remainder)
+}
+
+#[cfg(test)]
+mod arithmetic {
+    use super::*;
+
+    fn n(text: &str) -> Number {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        assert_eq!("12345678901234567890", n("12345678901234567890").to_string());
+        assert_eq!("1.50", n("1.50").to_string());
+        assert_eq!(".5", n("0.5").to_string());
+        assert_eq!("-.05", n("-.05").to_string());
+        assert_eq!("0", n("000").to_string());
+        assert_eq!("0", n("-0").to_string());
+        assert_eq!("0", n("0.000").to_string());
+    }
+
+    #[test]
+    fn parse_err_not_a_number() {
+        assert!("1.2.3".parse::<Number>().is_err());
+        assert!("".parse::<Number>().is_err());
+        assert!("1e5".parse::<Number>().is_err());
+    }
+
+    #[test]
+    fn add_and_sub() {
+        assert_eq!(".3", n("0.1").add(&n("0.2")).to_string());
+        assert_eq!("12345678901234567891", n("12345678901234567890").add(&n("1")).to_string());
+        assert_eq!("-1.25", n("1").sub(&n("2.25")).to_string());
+        assert_eq!("0", n("2.5").sub(&n("2.50")).to_string());
+    }
+
+    #[test]
+    fn mul_scale() {
+        assert_eq!("2.2", n("1.5").mul(&n("1.5"), 0).to_string());
+        assert_eq!("2.25", n("1.5").mul(&n("1.5"), 5).to_string());
+        assert_eq!("-6", n("-2").mul(&n("3"), 0).to_string());
+    }
+
+    #[test]
+    fn div_scale() {
+        assert_eq!("3", n("7").div(&n("2"), 0).unwrap().to_string());
+        assert_eq!("-3.50", n("-7").div(&n("2"), 2).unwrap().to_string());
+        assert_eq!(".3333", n("1").div(&n("3"), 4).unwrap().to_string());
+        assert_eq!("4", n("1").div(&n("0.25"), 0).unwrap().to_string());
+        assert_eq!(Err(String::from("Divide by zero")), n("1").div(&n("0.0"), 0));
+    }
+
+    #[test]
+    fn rem_scale() {
+        assert_eq!("1", n("7").rem(&n("2"), 0).unwrap().to_string());
+        assert_eq!("-1", n("-7").rem(&n("2"), 0).unwrap().to_string());
+        assert_eq!("1", n("7").rem(&n("-2"), 0).unwrap().to_string());
+        assert_eq!("1.5", n("7.5").rem(&n("2"), 0).unwrap().to_string());
+        assert_eq!("0", n("7.5").rem(&n("2"), 2).unwrap().to_string());
+        assert_eq!(3, n("7.5").rem(&n("2"), 3).unwrap().scale());
+        assert_eq!(".0001", n("1").rem(&n("3"), 4).unwrap().to_string());
+        assert_eq!(".00001", n("1").rem(&n("0.3"), 4).unwrap().to_string());
+        assert_eq!(Err(String::from("Divide by zero")), n("1").rem(&n("0"), 0));
+    }
+
+    #[test]
+    fn pow_scale() {
+        assert_eq!("1024", n("2").pow(&n("10"), 0).unwrap().to_string());
+        assert_eq!("1.4", n("1.1").pow(&n("4"), 0).unwrap().to_string());
+        assert_eq!("1.4641", n("1.1").pow(&n("4"), 10).unwrap().to_string());
+        assert_eq!("0", n("2").pow(&n("-1"), 0).unwrap().to_string());
+        assert_eq!(".25", n("2").pow(&n("-2"), 2).unwrap().to_string());
+        assert_eq!("1", n("5").pow(&n("0"), 0).unwrap().to_string());
+        assert_eq!("-8", n("-2").pow(&n("3.9"), 0).unwrap().to_string());
+    }
+
+    #[test]
+    fn compare_by_value() {
+        assert_eq!(n("1.50"), n("1.5"));
+        assert!(n("-1") < n("0.1"));
+        assert!(n("10") > n("9.99"));
+    }
+
+    #[test]
+    fn from_radix() {
+        assert_eq!(n("255"), Number::from_radix("FF", 16).unwrap());
+        assert_eq!(n("5"), Number::from_radix("101", 2).unwrap());
+        assert_eq!("10.5", Number::from_radix("A.8", 16).unwrap().to_string());
+        assert_eq!("1.2", Number::from_radix("1.2", 10).unwrap().to_string());
+        assert_eq!("165", Number::from_radix("FF", 10).unwrap().to_string());
+        assert!(Number::from_radix("1G", 16).is_err());
+    }
+
+    #[test]
+    fn to_string_radix() {
+        assert_eq!("FF", n("255").to_string_radix(16));
+        assert_eq!("-1010", n("-10").to_string_radix(2));
+        assert_eq!("0", n("0").to_string_radix(8));
+        assert_eq!(".8", n("0.5").to_string_radix(16));
+        assert_eq!("1.1000000", n("1.50").to_string_radix(2));
+        assert_eq!(" 02 05", n("45").to_string_radix(20));
+        assert_eq!(" 01.10", n("1.5").to_string_radix(20));
+    }
+
+    #[test]
+    fn integer_part() {
+        assert_eq!(Some(-12), n("-12.9").to_i64());
+        assert_eq!(None, n("99999999999999999999").to_i64());
+    }
+}
@@ -1,5 +1,7 @@
-use super::tokenizer::{Token, Tokenizer};
+use super::error::ThbcError;
+use super::tokenizer::{unescape, Keyword, RelOp, Span, Token, Tokenizer};
 use std::iter::Peekable;
+use std::rc::Rc;
 
 /**
  * thbc - Tar Heel Basic Calculator - Parser
@@ -13,14 +15,207 @@ use std::iter::Peekable;
  */
 
 /* == Begin Syntax Tree Elements == */
+/**
+ * The nodes that can fail when evaluated carry the span of their operator,
+ * so that runtime errors can point at it just like syntax errors do.
+ *
+ * Arrays have names of their own, so `a` and `a[0]` are unrelated. A whole
+ * array, written `a[]`, can only be passed to a function.
+ *
+ * `x op= y` is an OpAssign rather than `x = x op y`, so that the target's
+ * subscript is only evaluated once. The op of an IncDec is '+' or '-', and
+ * `post` tells `x++`, which is worth the old value, from `++x`.
+ */
 #[derive(Debug, PartialEq)]
 pub enum Expr {
     BinOp {
         lhs: Box<Expr>,
         op: char,
         rhs: Box<Expr>,
+        span: Span,
+    },
+    Neg(Box<Expr>),
+    Num(String),
+    Var(String),
+    Special(Special),
+    Builtin(Builtin, Box<Expr>),
+    Assign {
+        target: Box<Expr>,
+        value: Box<Expr>,
+        span: Span,
+    },
+    OpAssign {
+        target: Box<Expr>,
+        op: char,
+        value: Box<Expr>,
+        span: Span,
+    },
+    IncDec {
+        target: Box<Expr>,
+        op: char,
+        post: bool,
+        span: Span,
+    },
+    Rel {
+        lhs: Box<Expr>,
+        op: RelOp,
+        rhs: Box<Expr>,
+    },
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Call {
+        name: String,
+        args: Vec<Expr>,
+        span: Span,
+    },
+    Index {
+        name: String,
+        index: Box<Expr>,
+        span: Span,
+    },
+    Array(String),
+}
+
+impl Expr {
+    /**
+     * Places a node built by one of the factory functions below at the given
+     * span. Nodes that don't carry a span are returned unchanged.
+     */
+    pub fn at(mut self, at: Span) -> Expr {
+        match &mut self {
+            Expr::BinOp { span, .. }
+            | Expr::Assign { span, .. }
+            | Expr::OpAssign { span, .. }
+            | Expr::IncDec { span, .. }
+            | Expr::Call { span, .. }
+            | Expr::Index { span, .. } => *span = at,
+            _ => {}
+        }
+        self
+    }
+
+    /**
+     * Only variables, array elements and special variables can be assigned,
+     * incremented or decremented.
+     */
+    fn is_assignable(&self) -> bool {
+        matches!(self, Expr::Var(_) | Expr::Index { .. } | Expr::Special(_))
+    }
+
+    /**
+     * Passes on an expression that can be assigned and rejects any other,
+     * pointing at the operator that tried to assign it. The `source` of the
+     * expression names it in the error.
+     */
+    fn assignable(self, span: Span, source: &str) -> Result<Expr, ThbcError> {
+        if self.is_assignable() {
+            Ok(self)
+        } else {
+            Err(ThbcError::InvalidAssignment { target: String::from(source), span })
+        }
+    }
+}
+
+/**
+ * bc's special variables, which control how numbers are computed.
+ */
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Special {
+    Scale,
+    Ibase,
+    Obase,
+}
+
+/**
+ * bc's built-in functions of one argument.
+ */
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Builtin {
+    Scale,
+}
+
+/**
+ * A statement is the unit of input thbc evaluates. A bare expression prints
+ * its value, except for an assignment which, like bc, is evaluated silently.
+ * A block runs the statements in it in order, and an if runs `then` when
+ * its condition is nonzero and `otherwise`, if there is one, when it is 0.
+ *
+ * Loops run their body for as long as their condition is nonzero. A for
+ * loop may leave out any of its three expressions, and without a condition
+ * it only stops at a break. The span of a loop is that of its keyword.
+ *
+ * A print writes its items one after the other without a newline. A string
+ * on its own is a print of just that string.
+ */
+#[derive(Debug, PartialEq)]
+pub enum Stmt {
+    Expr(Expr),
+    Block(Vec<Stmt>),
+    If {
+        cond: Expr,
+        then: Box<Stmt>,
+        otherwise: Option<Box<Stmt>>,
+    },
+    While {
+        cond: Expr,
+        body: Box<Stmt>,
+        span: Span,
+    },
+    For {
+        init: Option<Expr>,
+        cond: Option<Expr>,
+        step: Option<Expr>,
+        body: Box<Stmt>,
+        span: Span,
     },
-    Num(f64),
+    Break,
+    Continue,
+    Define(Rc<Function>),
+    Return(Option<Expr>),
+    Print(Vec<PrintItem>),
+}
+
+/**
+ * Strings can only be printed, so they are not expressions.
+ */
+#[derive(Debug, PartialEq)]
+pub enum PrintItem {
+    Str(String),
+    Expr(Expr),
+}
+
+/**
+ * A function made with `define`. Like in bc, its parameters and autos hide
+ * the variables of the same name from the moment it is called until it
+ * returns, also in the functions it calls. Without a return it returns 0.
+ */
+#[derive(Debug, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<Param>,
+    pub autos: Vec<Param>,
+    pub body: Vec<Stmt>,
+}
+
+/**
+ * A parameter or auto is either a variable or, written `a[]`, an array.
+ * Arrays are passed by value, so a function never changes the array it was
+ * given.
+ */
+#[derive(Debug, PartialEq, Clone)]
+pub enum Param {
+    Var(String),
+    Array(String),
+}
+
+/**
+ * Everything parsed from one input, which may hold any number of statements
+ * separated by semicolons and newlines.
+ */
+#[derive(Debug, PartialEq)]
+pub struct Program {
+    pub stmts: Vec<Stmt>,
 }
 
 /* Helper factory functions for building Exprs */
@@ -29,31 +224,313 @@ pub fn binop(lhs: Expr, op: char, rhs: Expr) -> Expr {
         op,
         lhs: Box::new(lhs),
         rhs: Box::new(rhs),
+        span: Span::default(),
+    }
+}
+
+pub fn neg(operand: Expr) -> Expr {
+    Expr::Neg(Box::new(operand))
+}
+
+pub fn num(value: &str) -> Expr {
+    Expr::Num(String::from(value))
+}
+
+pub fn var(name: &str) -> Expr {
+    Expr::Var(String::from(name))
+}
+
+pub fn special(var: Special) -> Expr {
+    Expr::Special(var)
+}
+
+pub fn builtin(function: Builtin, arg: Expr) -> Expr {
+    Expr::Builtin(function, Box::new(arg))
+}
+
+pub fn assign(target: Expr, value: Expr) -> Expr {
+    Expr::Assign {
+        target: Box::new(target),
+        value: Box::new(value),
+        span: Span::default(),
+    }
+}
+pub fn op_assign(target: Expr, op: char, value: Expr) -> Expr {
+    Expr::OpAssign {
+        target: Box::new(target),
+        op,
+        value: Box::new(value),
+        span: Span::default(),
+    }
+}
+pub fn pre(op: char, target: Expr) -> Expr {
+    Expr::IncDec {
+        target: Box::new(target),
+        op,
+        post: false,
+        span: Span::default(),
+    }
+}
+pub fn post(target: Expr, op: char) -> Expr {
+    Expr::IncDec {
+        target: Box::new(target),
+        op,
+        post: true,
+        span: Span::default(),
+    }
+}
+pub fn rel(lhs: Expr, op: RelOp, rhs: Expr) -> Expr {
+    Expr::Rel {
+        op,
+        lhs: Box::new(lhs),
+        rhs: Box::new(rhs),
+    }
+}
+pub fn call(name: &str, args: Vec<Expr>) -> Expr {
+    Expr::Call {
+        name: String::from(name),
+        args,
+        span: Span::default(),
     }
 }
+pub fn index(name: &str, index: Expr) -> Expr {
+    Expr::Index {
+        name: String::from(name),
+        index: Box::new(index),
+        span: Span::default(),
+    }
+}
+pub fn and(lhs: Expr, rhs: Expr) -> Expr {
+    Expr::And(Box::new(lhs), Box::new(rhs))
+}
+
+pub fn or(lhs: Expr, rhs: Expr) -> Expr {
+    Expr::Or(Box::new(lhs), Box::new(rhs))
+}
 
-pub fn num(value: f64) -> Expr {
-    Expr::Num(value)
+pub fn not(operand: Expr) -> Expr {
+    Expr::Not(Box::new(operand))
 }
 /* == End Syntax Tree Elements == */
 
+/**
+ * Besides the tokens still to be parsed, a Parser keeps the input they come
+ * from and the span of the last token it took, so that errors can point at
+ * the input and quote it. It knows how many loops it is inside of and
+ * whether it is in a function, so that it can reject a stray break,
+ * continue or return, and counts how deeply nested the statement or
+ * expression being parsed is, so that a pathological input is rejected
+ * before the recursion runs out of stack.
+ */
 pub struct Parser<'tokens> {
+    source: &'tokens str,
     tokens: Peekable<Tokenizer<'tokens>>,
+    span: Span,
+    loops: usize,
+    in_function: bool,
+    depth: usize,
 }
 
+/**
+ * How deeply blocks, bodies, parentheses and chains of prefix operators or
+ * right associative operators may be nested, all counted together.
+ */
+const MAX_DEPTH: usize = 256;
+
 impl<'tokens> Parser<'tokens> {
-    pub fn parse(tokenizer: Tokenizer<'tokens>) -> Result<Expr, String> {
+    /**
+     * Parses an input that must be a single expression and nothing else.
+     * thbc itself always parses programs, so this is only the entry point
+     * for tests about expressions.
+     */
+    #[cfg(test)]
+    pub fn parse(tokenizer: Tokenizer<'tokens>) -> Result<Expr, ThbcError> {
         let mut parser = Parser {
+            source: tokenizer.input(),
             tokens: tokenizer.peekable(),
+            span: Span::default(),
+            loops: 0,
+            in_function: false,
+            depth: 0,
         };
-        // TODO lvl0: Ensure no remaining tokens in parser after parsing Expr
-        let mut parse = parser.expr(); //calling this should consume all the tokens in the input, stores the result in 'parse'
-        let next = parser.take_next_token(); //checks to see if there is another token after calling expr()
-        match next {
-            Ok(token) => {
-                Err(format!("Expected end of input, found {:?}", token)) //if there is another token, throws an error
-            },
-            Err(e) => parse, //otherwise, returns the result of calling expr() on parser
+        let parse = parser.expr()?; //calling this should consume all the tokens in the input, stores the result in 'parse'
+        if parser.at_end() {
+            return Ok(parse); //returns the result of calling expr() on parser if nothing is left
+        }
+        match parser.take_next_token()? { //otherwise throws an error about the token after the expr
+            Token::RParen => Err(ThbcError::UnbalancedParen { span: parser.span }),
+            found => Err(parser.unexpected(found, vec!["an operator", "end of input"])),
+        }
+    }
+
+    /**
+     * Parses a whole input, such as a line typed in or an entire script, as
+     * a list of statements.
+     */
+    pub fn parse_program(tokenizer: Tokenizer<'tokens>) -> Result<Program, ThbcError> {
+        let mut parser = Parser {
+            source: tokenizer.input(),
+            tokens: tokenizer.peekable(),
+            span: Span::default(),
+            loops: 0,
+            in_function: false,
+            depth: 0,
+        };
+        let stmts = parser.stmt_list(true)?;
+        if parser.at_end() {
+            return Ok(Program { stmts });
+        }
+        let found = parser.take_next_token()?; //only a '}' without a '{' can stop the list early
+        Err(parser.unexpected(found, vec!["a statement"]))
+    }
+}
+
+/**
+ * The factory functions build nodes without a span, so the tests that only
+ * care about the shape of what was parsed erase the spans in it before
+ * comparing. The tests about where nodes and errors are keep them.
+ */
+#[cfg(test)]
+trait EraseSpans: Sized {
+    fn erase_spans(&mut self);
+
+    fn without_spans(mut self) -> Self {
+        self.erase_spans();
+        self
+    }
+}
+
+#[cfg(test)]
+impl EraseSpans for Expr {
+    fn erase_spans(&mut self) {
+        match self {
+            Expr::BinOp { lhs, rhs, span, .. } => {
+                lhs.erase_spans();
+                rhs.erase_spans();
+                *span = Span::default();
+            }
+            Expr::Assign { target, value, span } | Expr::OpAssign { target, value, span, .. } => {
+                target.erase_spans();
+                value.erase_spans();
+                *span = Span::default();
+            }
+            Expr::IncDec { target, span, .. } => {
+                target.erase_spans();
+                *span = Span::default();
+            }
+            Expr::Call { args, span, .. } => {
+                args.erase_spans();
+                *span = Span::default();
+            }
+            Expr::Index { index, span, .. } => {
+                index.erase_spans();
+                *span = Span::default();
+            }
+            Expr::Rel { lhs, rhs, .. } | Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+                lhs.erase_spans();
+                rhs.erase_spans();
+            }
+            Expr::Neg(operand) | Expr::Builtin(_, operand) | Expr::Not(operand) => operand.erase_spans(),
+            Expr::Num(_) | Expr::Var(_) | Expr::Special(_) | Expr::Array(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+impl EraseSpans for Stmt {
+    fn erase_spans(&mut self) {
+        match self {
+            Stmt::Expr(expr) => expr.erase_spans(),
+            Stmt::Block(stmts) => stmts.erase_spans(),
+            Stmt::If { cond, then, otherwise } => {
+                cond.erase_spans();
+                then.erase_spans();
+                otherwise.erase_spans();
+            }
+            Stmt::While { cond, body, span } => {
+                cond.erase_spans();
+                body.erase_spans();
+                *span = Span::default();
+            }
+            Stmt::For { init, cond, step, body, span } => {
+                init.erase_spans();
+                cond.erase_spans();
+                step.erase_spans();
+                body.erase_spans();
+                *span = Span::default();
+            }
+            Stmt::Define(function) => {
+                Rc::get_mut(function).expect("a parsed function is not shared yet").body.erase_spans()
+            }
+            Stmt::Return(value) => value.erase_spans(),
+            Stmt::Print(items) => {
+                for item in items {
+                    if let PrintItem::Expr(expr) = item {
+                        expr.erase_spans();
+                    }
+                }
+            }
+            Stmt::Break | Stmt::Continue => {}
+        }
+    }
+}
+
+#[cfg(test)]
+impl EraseSpans for Program {
+    fn erase_spans(&mut self) {
+        self.stmts.erase_spans();
+    }
+}
+
+#[cfg(test)]
+impl EraseSpans for ThbcError {
+    fn erase_spans(&mut self) {
+        match self {
+            ThbcError::Lex { span, .. }
+            | ThbcError::UnexpectedToken { span, .. }
+            | ThbcError::UnexpectedEof { span, .. }
+            | ThbcError::UnbalancedParen { span }
+            | ThbcError::InvalidAssignment { span, .. }
+            | ThbcError::Runtime { span, .. }
+            | ThbcError::OutsideLoop { span, .. }
+            | ThbcError::OutsideFunction { span, .. }
+            | ThbcError::TooDeep { span } => *span = Span::default(),
+            ThbcError::Backend { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+impl<T: EraseSpans> EraseSpans for Box<T> {
+    fn erase_spans(&mut self) {
+        (**self).erase_spans();
+    }
+}
+
+#[cfg(test)]
+impl<T: EraseSpans> EraseSpans for Option<T> {
+    fn erase_spans(&mut self) {
+        if let Some(value) = self {
+            value.erase_spans();
+        }
+    }
+}
+
+#[cfg(test)]
+impl<T: EraseSpans> EraseSpans for Vec<T> {
+    fn erase_spans(&mut self) {
+        for value in self {
+            value.erase_spans();
+        }
+    }
+}
+
+#[cfg(test)]
+impl<T: EraseSpans, E: EraseSpans> EraseSpans for Result<T, E> {
+    fn erase_spans(&mut self) {
+        match self {
+            Ok(value) => value.erase_spans(),
+            Err(error) => error.erase_spans(),
         }
     }
 }
@@ -62,26 +539,38 @@ impl<'tokens> Parser<'tokens> {
 mod public_api {
     use super::*;
 
+    /**
+     * Parses an expression with its spans erased, to compare it with a tree
+     * built by the factory functions.
+     */
+    fn parse(input: &str) -> Result<Expr, ThbcError> {
+        Parser::parse(Tokenizer::new(input)).without_spans()
+    }
+
+    fn span(start: usize, end: usize, line: usize, column: usize) -> Span {
+        Span { start, end, line, column }
+    }
+
     mod lvl0 {
         use super::*;
 
         #[test]
         fn parse_atom_number() {
-            let res = Parser::parse(Tokenizer::new("1")).unwrap();
-            assert_eq!(num(1.0), res);
+            let res = parse("1").unwrap();
+            assert_eq!(num("1"), res);
         }
 
         #[test]
         fn parse_atom_parens() {
-            let res = Parser::parse(Tokenizer::new("(1)")).unwrap();
-            assert_eq!(num(1.0), res);
+            let res = parse("(1)").unwrap();
+            assert_eq!(num("1"), res);
         }
 
         #[test]
         fn parse_err_did_not_consume_whole_input() {
-            let res = Parser::parse(Tokenizer::new("1 2"));
+            let res = parse("1 2");
             assert_eq!(
-                Err(String::from("Expected end of input, found Number(2.0)")),
+                Err(ThbcError::UnexpectedToken { found: Token::Number(String::from("2")), expected: vec!["an operator", "end of input"], span: Span::default() }),
                 res
             );
         }
@@ -92,14 +581,14 @@ mod public_api {
 
         #[test]
         fn parse_mul() {
-            let res = Parser::parse(Tokenizer::new("1*2")).unwrap();
-            assert_eq!(binop(num(1.0), '*', num(2.0)), res);
+            let res = parse("1*2").unwrap();
+            assert_eq!(binop(num("1"), '*', num("2")), res);
         }
 
         #[test]
         fn parse_div() {
-            let res = Parser::parse(Tokenizer::new("1/2")).unwrap();
-            assert_eq!(binop(num(1.0), '/', num(2.0)), res);
+            let res = parse("1/2").unwrap();
+            assert_eq!(binop(num("1"), '/', num("2")), res);
         }
     }
 
@@ -108,111 +597,1367 @@ mod public_api {
 
         #[test]
         fn parse_div_chain() {
-            let res = Parser::parse(Tokenizer::new("1/2/4")).unwrap();
-            assert_eq!(binop(binop(num(1.0), '/', num(2.0)), '/', num(4.0)), res);
+            let res = parse("1/2/4").unwrap();
+            assert_eq!(binop(binop(num("1"), '/', num("2")), '/', num("4")), res);
         }
 
         // TODO: add additional lvl2 tests
+
+        #[test]
+        fn parse_mod_with_mul_div() {
+            let res = parse("7%3*2/1").unwrap();
+            assert_eq!(binop(binop(binop(num("7"), '%', num("3")), '*', num("2")), '/', num("1")), res);
+        }
+
+        #[test]
+        fn parse_mod_tighter_than_add() {
+            let res = parse("1+7%3").unwrap();
+            assert_eq!(binop(num("1"), '+', binop(num("7"), '%', num("3"))), res);
+        }
         
         #[test]
         fn parse_mul_chain() {
-            let res = Parser::parse(Tokenizer::new("1*2*4")).unwrap();
-            assert_eq!(binop(binop(num(1.0), '*', num(2.0)), '*', num(4.0)), res);
+            let res = parse("1*2*4").unwrap();
+            assert_eq!(binop(binop(num("1"), '*', num("2")), '*', num("4")), res);
         }
         
         #[test]
         fn parse_mul_and_div() {
-            let res = Parser::parse(Tokenizer::new("1/2*4")).unwrap();
-            assert_eq!(binop(binop(num(1.0), '/', num(2.0)), '*', num(4.0)), res);
+            let res = parse("1/2*4").unwrap();
+            assert_eq!(binop(binop(num("1"), '/', num("2")), '*', num("4")), res);
         }
 
         #[test]
         fn parse_with_parens() {
-            let res = Parser::parse(Tokenizer::new("1*(2*4)")).unwrap();
-            assert_eq!(binop(num(1.0), '*', binop(num(2.0), '*', num(4.0))), res);
+            let res = parse("1*(2*4)").unwrap();
+            assert_eq!(binop(num("1"), '*', binop(num("2"), '*', num("4"))), res);
         }
 
         #[test]
         fn parse_with_bunch_of_parens() {
-            let res = Parser::parse(Tokenizer::new("(1*((2*3)*4))")).unwrap();
-            assert_eq!(binop(num(1.0), '*', binop(binop(num(2.0), '*', num(3.0)), '*', num(4.0))), res);
+            let res = parse("(1*((2*3)*4))").unwrap();
+            assert_eq!(binop(num("1"), '*', binop(binop(num("2"), '*', num("3")), '*', num("4"))), res);
+        }
+
+    }
+
+    mod lvl3 {
+        use super::*;
+
+        #[test]
+        fn parse_add() {
+            let res = parse("1+2").unwrap();
+            assert_eq!(binop(num("1"), '+', num("2")), res);
+        }
+
+        #[test]
+        fn parse_sub() {
+            let res = parse("1-2").unwrap();
+            assert_eq!(binop(num("1"), '-', num("2")), res);
+        }
+
+        #[test]
+        fn parse_multiple_add_or_sub() {
+            let res = parse("1+2-3+4-5").unwrap();
+            assert_eq!(binop(binop(binop(binop(num("1"), '+', num("2")), '-', num("3")), '+', num("4")), '-', num("5")), res);
+        }
+
+        #[test]
+        fn parse_add_and_mult() {
+            let res = parse("1+2*3").unwrap();
+            assert_eq!(binop(num("1"), '+', binop(num("2"), '*', num("3"))), res);
+        }
+
+        #[test]
+        fn parse_stuff() {
+            let res = parse("(1+2)/(4-3)").unwrap();
+            assert_eq!(binop(binop(num("1"), '+', num("2")), '/', binop(num("4"), '-', num("3"))), res);
+        }
+        
+    }
+
+    mod pow {
+        use super::*;
+
+        #[test]
+        fn parse_pow() {
+            let res = parse("2^3").unwrap();
+            assert_eq!(binop(num("2"), '^', num("3")), res);
+        }
+
+        #[test]
+        fn parse_pow_right_associative() {
+            let res = parse("2^3^2").unwrap();
+            assert_eq!(binop(num("2"), '^', binop(num("3"), '^', num("2"))), res);
+        }
+
+        #[test]
+        fn parse_pow_binds_tighter_than_mul() {
+            let res = parse("2*3^2/4").unwrap();
+            assert_eq!(binop(binop(num("2"), '*', binop(num("3"), '^', num("2"))), '/', num("4")), res);
+        }
+
+        #[test]
+        fn parse_pow_with_parens() {
+            let res = parse("(1+1)^2-1").unwrap();
+            assert_eq!(binop(binop(binop(num("1"), '+', num("1")), '^', num("2")), '-', num("1")), res);
+        }
+    }
+
+    mod unary {
+        use super::*;
+
+        #[test]
+        fn parse_neg() {
+            let res = parse("-3*2").unwrap();
+            assert_eq!(binop(neg(num("3")), '*', num("2")), res);
+        }
+
+        #[test]
+        fn parse_neg_rhs() {
+            let res = parse("2*-(1+1)").unwrap();
+            assert_eq!(binop(num("2"), '*', neg(binop(num("1"), '+', num("1")))), res);
+        }
+
+        #[test]
+        fn parse_neg_binds_tighter_than_pow() {
+            let res = parse("-2^-1").unwrap();
+            assert_eq!(binop(neg(num("2")), '^', neg(num("1"))), res);
+        }
+
+        #[test]
+        fn parse_unary_plus() {
+            let res = parse("+1-+2").unwrap();
+            assert_eq!(binop(num("1"), '-', num("2")), res);
+        }
+    }
+
+    mod assign {
+        use super::*;
+
+        #[test]
+        fn parse_var() {
+            let res = parse("x+1").unwrap();
+            assert_eq!(binop(var("x"), '+', num("1")), res);
+        }
+
+        #[test]
+        fn parse_assign() {
+            let res = parse("x = 3*4").unwrap();
+            assert_eq!(assign(var("x"), binop(num("3"), '*', num("4"))), res);
+        }
+
+        #[test]
+        fn parse_long_names() {
+            let res = parse("total = sub_1 + x2").unwrap();
+            assert_eq!(assign(var("total"), binop(var("sub_1"), '+', var("x2"))), res);
+        }
+
+        #[test]
+        fn parse_assign_chain() {
+            let res = parse("a = b = 1").unwrap();
+            assert_eq!(assign(var("a"), assign(var("b"), num("1"))), res);
+        }
+
+        #[test]
+        fn parse_assign_in_parens() {
+            let res = parse("(a = 2) * a").unwrap();
+            assert_eq!(binop(assign(var("a"), num("2")), '*', var("a")), res);
+        }
+
+        #[test]
+        fn parse_err_assign_to_non_register() {
+            let res = parse("1 = 2");
+            assert_eq!(Err(ThbcError::InvalidAssignment { target: String::from("1"), span: Span::default() }), res);
+        }
+
+        #[test]
+        fn parse_program_statement() {
+            let res = Parser::parse_program(Tokenizer::new("y = y + 1\n")).without_spans().unwrap();
+            assert_eq!(vec![Stmt::Expr(assign(var("y"), binop(var("y"), '+', num("1"))))], res.stmts);
+        }
+
+        #[test]
+        fn parse_op_assign() {
+            let res = parse("a[i] ^= x -= 2 * y").unwrap();
+            let inner = op_assign(var("x"), '-', binop(num("2"), '*', var("y")));
+            assert_eq!(op_assign(index("a", var("i")), '^', inner), res);
+        }
+
+        #[test]
+        fn parse_inc_dec() {
+            let res = parse("-x++ * --scale").unwrap();
+            assert_eq!(binop(neg(post(var("x"), '+')), '*', pre('-', special(Special::Scale))), res);
+            let res = parse("++a[i--]").unwrap();
+            assert_eq!(pre('+', index("a", post(var("i"), '-'))), res);
+        }
+
+        #[test]
+        fn parse_err_inc_dec_non_register() {
+            let res = parse("++2");
+            assert_eq!(Err(ThbcError::InvalidAssignment { target: String::from("2"), span: Span::default() }), res);
+            let res = parse("2++");
+            assert_eq!(Err(ThbcError::UnexpectedToken { found: Token::Increment, expected: vec!["an operator", "end of input"], span: Span::default() }), res);
+        }
+
+        #[test]
+        fn parse_err_op_assign_non_register() {
+            let res = parse("x + 1 += 2");
+            assert_eq!(Err(ThbcError::InvalidAssignment { target: String::from("x + 1"), span: Span::default() }), res);
+            let res = parse("y = (x+1) = 2");
+            assert_eq!(Err(ThbcError::InvalidAssignment { target: String::from("(x+1)"), span: Span::default() }), res);
+        }
+    }
+
+    mod scale {
+        use super::*;
+
+        #[test]
+        fn parse_scale_assign() {
+            let res = parse("scale = 5").unwrap();
+            assert_eq!(assign(special(Special::Scale), num("5")), res);
+        }
+
+        #[test]
+        fn parse_scale_read() {
+            let res = parse("scale + s").unwrap();
+            assert_eq!(binop(special(Special::Scale), '+', var("s")), res);
+        }
+
+        #[test]
+        fn parse_scale_builtin() {
+            let res = parse("scale(1.25) * 2").unwrap();
+            assert_eq!(binop(builtin(Builtin::Scale, num("1.25")), '*', num("2")), res);
+        }
+
+        #[test]
+        fn parse_err_assign_to_builtin() {
+            let res = parse("scale(1) = 2");
+            assert_eq!(Err(ThbcError::InvalidAssignment { target: String::from("scale(1)"), span: Span::default() }), res);
+        }
+    }
+
+    mod base {
+        use super::*;
+
+        #[test]
+        fn parse_obase_assign() {
+            let res = parse("obase = 16").unwrap();
+            assert_eq!(assign(special(Special::Obase), num("16")), res);
+        }
+
+        #[test]
+        fn parse_hex_literal() {
+            let res = parse("ibase = A").unwrap();
+            assert_eq!(assign(special(Special::Ibase), num("A")), res);
+        }
+
+        #[test]
+        fn parse_base_reads() {
+            let res = parse("ibase*obase").unwrap();
+            assert_eq!(binop(special(Special::Ibase), '*', special(Special::Obase)), res);
+        }
+    }
+
+    mod precedence {
+        use super::*;
+
+        #[test]
+        fn parse_sub_after_mul() {
+            let res = parse("1-2*3-4").unwrap();
+            assert_eq!(binop(binop(num("1"), '-', binop(num("2"), '*', num("3"))), '-', num("4")), res);
+        }
+    }
+
+    mod rel {
+        use super::*;
+
+        #[test]
+        fn parse_rel() {
+            let res = parse("1 + 2 <= 3 * 4").unwrap();
+            assert_eq!(rel(binop(num("1"), '+', num("2")), RelOp::Le, binop(num("3"), '*', num("4"))), res);
+        }
+
+        #[test]
+        fn parse_rel_left_associative() {
+            let res = parse("1 < 2 != 0").unwrap();
+            assert_eq!(rel(rel(num("1"), RelOp::Lt, num("2")), RelOp::Ne, num("0")), res);
+        }
+
+        #[test]
+        fn parse_rel_looser_than_assign() {
+            let res = parse("a = 1 == b = 2").unwrap();
+            assert_eq!(rel(assign(var("a"), num("1")), RelOp::Eq, assign(var("b"), num("2"))), res);
+        }
+
+        #[test]
+        fn parse_err_missing_rhs() {
+            let res = parse("1 >");
+            assert_eq!(Err(ThbcError::UnexpectedEof { expected: vec!["an expression"], span: Span::default() }), res);
+        }
+    }
+
+    mod logic {
+        use super::*;
+
+        #[test]
+        fn parse_and_binds_tighter_than_or() {
+            let res = parse("a || b && c || d").unwrap();
+            assert_eq!(or(or(var("a"), and(var("b"), var("c"))), var("d")), res);
+        }
+
+        #[test]
+        fn parse_not_looser_than_rel() {
+            let res = parse("!a < b && !!c").unwrap();
+            assert_eq!(and(not(rel(var("a"), RelOp::Lt, var("b"))), not(not(var("c")))), res);
+        }
+
+        #[test]
+        fn parse_not_as_operand() {
+            let res = parse("1 + !0 + 1").unwrap();
+            assert_eq!(binop(num("1"), '+', not(binop(num("0"), '+', num("1")))), res);
+        }
+
+        #[test]
+        fn parse_err_missing_rhs() {
+            let res = parse("1 &&");
+            assert_eq!(Err(ThbcError::UnexpectedEof { expected: vec!["an expression"], span: Span::default() }), res);
+        }
+    }
+
+    mod program {
+        use super::*;
+
+        fn stmts(input: &str) -> Vec<Stmt> {
+            Parser::parse_program(Tokenizer::new(input)).without_spans().unwrap().stmts
+        }
+
+        fn program_err(input: &str) -> ThbcError {
+            Parser::parse_program(Tokenizer::new(input)).without_spans().unwrap_err()
+        }
+
+        fn located_err(input: &str) -> ThbcError {
+            Parser::parse_program(Tokenizer::new(input)).unwrap_err()
+        }
+
+        #[test]
+        fn parse_print() {
+            let items = vec![
+                PrintItem::Str(String::from("total: ")),
+                PrintItem::Expr(binop(var("t"), '*', num("2"))),
+                PrintItem::Str(String::from("\n")),
+            ];
+            assert_eq!(
+                vec![Stmt::Print(items), Stmt::Print(vec![PrintItem::Str(String::from("done"))])],
+                stmts("print \"total: \", t * 2, \"\\n\"; \"done\"")
+            );
+        }
+
+        #[test]
+        fn parse_string_statement_keeps_escapes() {
+            assert_eq!(
+                vec![
+                    Stmt::Print(vec![PrintItem::Str(String::from("a\\nb\\x"))]),
+                    Stmt::Print(vec![PrintItem::Str(String::from("a\nb"))]),
+                ],
+                stmts("\"a\\nb\\x\"; print \"a\\nb\"")
+            );
+        }
+
+        #[test]
+        fn parse_print_err_escape() {
+            assert_eq!(
+                ThbcError::Lex { message: String::from("Unknown escape \\x in string"), span: span(9, 15, 1, 10) },
+                located_err("print 1, \"a\\xb\"")
+            );
+            assert_eq!(
+                ThbcError::Lex { message: String::from("Unfinished escape in string"), span: Span::default() },
+                program_err("print \"ab\\\"")
+            );
+        }
+
+        #[test]
+        fn parse_err_string_in_expr() {
+            assert_eq!(
+                ThbcError::UnexpectedToken {
+                    found: Token::Str(String::from("a")),
+                    expected: vec!["an expression"],
+                    span: Span::default(),
+                },
+                program_err("1 + \"a\"")
+            );
+            assert_eq!(
+                ThbcError::UnexpectedToken {
+                    found: Token::Operator('+'),
+                    expected: vec!["an operator", "';'", "a newline"],
+                    span: Span::default(),
+                },
+                program_err("\"a\" + 1")
+            );
+        }
+
+        #[test]
+        fn parse_program_separators() {
+            assert_eq!(
+                vec![Stmt::Expr(num("1")), Stmt::Expr(assign(var("x"), num("2"))), Stmt::Expr(var("x"))],
+                stmts("1; x = 2\nx\n")
+            );
+        }
+
+        #[test]
+        fn parse_program_empty_statements() {
+            assert_eq!(Vec::<Stmt>::new(), stmts(""));
+            assert_eq!(Vec::<Stmt>::new(), stmts("\n;;\n"));
+            assert_eq!(vec![Stmt::Expr(num("1"))], stmts(";\n 1 ;;"));
+        }
+
+        #[test]
+        fn parse_program_blocks() {
+            assert_eq!(
+                vec![
+                    Stmt::Block(vec![Stmt::Expr(num("1")), Stmt::Block(vec![]), Stmt::Expr(num("2"))]),
+                    Stmt::Expr(num("3")),
+                ],
+                stmts("{1\n{}; 2;}\n3")
+            );
+        }
+
+        #[test]
+        fn parse_program_if() {
+            assert_eq!(
+                vec![Stmt::If {
+                    cond: rel(var("x"), RelOp::Lt, num("2")),
+                    then: Box::new(Stmt::Expr(num("1"))),
+                    otherwise: None,
+                }],
+                stmts("if (x < 2) 1")
+            );
+        }
+
+        #[test]
+        fn parse_program_if_else() {
+            assert_eq!(
+                vec![
+                    Stmt::If {
+                        cond: var("x"),
+                        then: Box::new(Stmt::Block(vec![Stmt::Expr(num("1"))])),
+                        otherwise: Some(Box::new(Stmt::If {
+                            cond: var("y"),
+                            then: Box::new(Stmt::Expr(num("2"))),
+                            otherwise: Some(Box::new(Stmt::Expr(num("3")))),
+                        })),
+                    },
+                    Stmt::Expr(num("4")),
+                ],
+                stmts("if (x)\n{\n1\n} else\nif (y) 2 else 3; 4")
+            );
+        }
+
+        #[test]
+        fn parse_program_else_on_next_line() {
+            assert_eq!(
+                ThbcError::UnexpectedToken {
+                    found: Token::Keyword(Keyword::Else),
+                    expected: vec!["an expression"],
+                    span: Span::default(),
+                },
+                program_err("if (x) 1\nelse 2")
+            );
+        }
+
+        #[test]
+        fn parse_program_err_if_without_paren() {
+            assert_eq!(
+                ThbcError::UnexpectedToken {
+                    found: Token::Ident(String::from("x")),
+                    expected: vec!["'('"],
+                    span: Span::default(),
+                },
+                program_err("if x 1")
+            );
+        }
+
+        #[test]
+        fn parse_program_while() {
+            assert_eq!(
+                vec![Stmt::While {
+                    cond: var("x"),
+                    body: Box::new(Stmt::Block(vec![Stmt::Break, Stmt::Continue])),
+                    span: Span::default(),
+                }],
+                stmts("while (x)\n{ break; continue }")
+            );
+        }
+
+        #[test]
+        fn parse_program_for() {
+            assert_eq!(
+                vec![Stmt::For {
+                    init: Some(assign(var("i"), num("0"))),
+                    cond: Some(rel(var("i"), RelOp::Lt, num("3"))),
+                    step: Some(assign(var("i"), binop(var("i"), '+', num("1")))),
+                    body: Box::new(Stmt::Expr(var("i"))),
+                    span: Span::default(),
+                }],
+                stmts("for (i = 0; i < 3; i = i + 1) i")
+            );
+            assert_eq!(
+                vec![Stmt::For {
+                    init: None,
+                    cond: None,
+                    step: None,
+                    body: Box::new(Stmt::Break),
+                    span: Span::default(),
+                }],
+                stmts("for (;;) break")
+            );
+        }
+
+        #[test]
+        fn parse_program_err_for_missing_semicolon() {
+            assert_eq!(
+                ThbcError::UnexpectedToken {
+                    found: Token::Ident(String::from("i")),
+                    expected: vec!["';'"],
+                    span: span(9, 10, 1, 10),
+                },
+                located_err("for (i=0 i<3; i++) i")
+            );
+        }
+
+        #[test]
+        fn parse_program_err_break_outside_loop() {
+            assert_eq!(
+                ThbcError::OutsideLoop { keyword: "break", span: span(13, 18, 1, 14) },
+                located_err("while (1) 1; break")
+            );
+            assert_eq!(
+                ThbcError::OutsideLoop { keyword: "continue", span: Span::default() },
+                program_err("if (1) { continue }")
+            );
+        }
+
+        #[test]
+        fn parse_program_err_missing_separator() {
+            assert_eq!(
+                ThbcError::UnexpectedToken {
+                    found: Token::Number(String::from("2")),
+                    expected: vec!["an operator", "';'", "a newline"],
+                    span: Span::default()
+                },
+                program_err("1 2")
+            );
+        }
+
+        #[test]
+        fn parse_program_err_unclosed_block() {
+            assert_eq!(
+                ThbcError::UnexpectedEof { expected: vec!["'}'"], span: Span::default() },
+                program_err("{1\n2\n")
+            );
+        }
+
+        #[test]
+        fn parse_program_err_stray_brace() {
+            assert_eq!(
+                ThbcError::UnexpectedToken { found: Token::RBrace, expected: vec!["a statement"], span: Span::default() },
+                program_err("1; }")
+            );
+        }
+
+        #[test]
+        fn parse_program_err_points_at_later_line() {
+            let span = located_err("1\n2 +\n").span().unwrap();
+            assert_eq!((2, 4), (span.line, span.column));
+        }
+    }
+
+    mod functions {
+        use super::*;
+
+        fn stmts(input: &str) -> Vec<Stmt> {
+            Parser::parse_program(Tokenizer::new(input)).without_spans().unwrap().stmts
+        }
+
+        fn program_err(input: &str) -> ThbcError {
+            Parser::parse_program(Tokenizer::new(input)).without_spans().unwrap_err()
+        }
+
+        #[test]
+        fn parse_define() {
+            let function = Function {
+                name: String::from("f"),
+                params: vec![Param::Var(String::from("x")), Param::Array(String::from("n"))],
+                autos: vec![Param::Var(String::from("y"))],
+                body: vec![
+                    Stmt::Expr(assign(var("y"), binop(var("x"), '*', index("n", num("0"))))),
+                    Stmt::Return(Some(binop(var("y"), '+', num("1")))),
+                ],
+            };
+            assert_eq!(
+                vec![Stmt::Define(Rc::new(function))],
+                stmts("define f(x, n[])\n{\n auto y\n y = x*n[0]; return (y+1)\n}")
+            );
+        }
+
+        #[test]
+        fn parse_define_without_params() {
+            let function = Function {
+                name: String::from("g"),
+                params: vec![],
+                autos: vec![],
+                body: vec![Stmt::Return(None), Stmt::Return(Some(var("x")))],
+            };
+            assert_eq!(vec![Stmt::Define(Rc::new(function))], stmts("define g() { return; return x }"));
+        }
+
+        #[test]
+        fn parse_call() {
+            assert_eq!(
+                Ok(binop(call("f", vec![num("1"), binop(var("x"), '+', num("2"))]), '*', call("g", vec![]))),
+                parse("f(1, x + 2) * g()")
+            );
+            assert_eq!(Ok(call("f", vec![call("f", vec![var("f")])])), parse("f(f(f))"));
+        }
+
+        #[test]
+        fn parse_call_err_missing_comma() {
+            assert_eq!(
+                Err(ThbcError::UnexpectedToken {
+                    found: Token::Number(String::from("2")),
+                    expected: vec!["','", "')'"],
+                    span: Span::default(),
+                }),
+                parse("f(1 2)")
+            );
+            assert_eq!(Err(ThbcError::UnbalancedParen { span: Span::default() }), parse("f(1, 2"));
+        }
+
+        #[test]
+        fn parse_err_return_outside_function() {
+            let err = program_err("{ return 1 }");
+            assert_eq!(ThbcError::OutsideFunction { keyword: "return", span: Span::default() }, err);
+            assert_eq!("E0009", err.code());
+        }
+
+        #[test]
+        fn parse_err_break_in_function_outside_loop() {
+            assert_eq!(
+                ThbcError::OutsideLoop { keyword: "break", span: Span::default() },
+                program_err("define f() { while (1) 1; break }")
+            );
+        }
+
+        #[test]
+        fn parse_err_define_below_top_level() {
+            let nested = ThbcError::UnexpectedToken {
+                found: Token::Keyword(Keyword::Define),
+                expected: vec!["a statement"],
+                span: Span::default(),
+            };
+            assert_eq!(
+                nested,
+                program_err("define f(x) { define g(y) { return y*2 }; return g(x) }")
+            );
+            assert_eq!(nested, program_err("if (1) define h() { return 7 }"));
+            assert_eq!(nested, program_err("{ define h() { return 7 } }"));
+            assert_eq!(nested, program_err("while (1) define h() { return 7 }"));
+            assert_eq!(nested, program_err("for (;;) { 1; define h() { return 7 } }"));
+            let err = Parser::parse_program(Tokenizer::new("if (1) define h() {}")).unwrap_err();
+            assert_eq!(Some(span(7, 13, 1, 8)), err.span());
+        }
+
+        #[test]
+        fn parse_define_at_top_level_between_statements() {
+            let stmts = stmts("x = 1; define f() { return 2 }\nf()");
+            assert_eq!(3, stmts.len());
+            assert!(matches!(stmts[1], Stmt::Define(_)));
+        }
+
+        #[test]
+        fn parse_err_bad_param() {
+            assert_eq!(
+                ThbcError::UnexpectedToken {
+                    found: Token::Number(String::from("1")),
+                    expected: vec!["a name"],
+                    span: Span::default(),
+                },
+                program_err("define f(x, 1) {}")
+            );
+        }
+
+        #[test]
+        fn parse_index() {
+            assert_eq!(
+                Ok(assign(index("a", binop(var("i"), '+', num("1"))), binop(index("a", var("i")), '*', num("2")))),
+                parse("a[i + 1] = a[i] * 2")
+            );
+        }
+
+        #[test]
+        fn parse_array_arg() {
+            assert_eq!(
+                Ok(call("f", vec![Expr::Array(String::from("a")), index("a", num("0")), var("a")])),
+                parse("f(a[], a[0], a)")
+            );
+        }
+
+        #[test]
+        fn parse_err_whole_array_outside_call() {
+            assert_eq!(
+                Err(ThbcError::UnexpectedToken { found: Token::RBracket, expected: vec!["an expression"], span: Span::default() }),
+                parse("a[] + 1")
+            );
+        }
+
+        #[test]
+        fn parse_err_unclosed_subscript() {
+            assert_eq!(
+                Err(ThbcError::UnexpectedToken { found: Token::RParen, expected: vec!["']'"], span: Span::default() }),
+                parse("(a[1)")
+            );
+        }
+    }
+
+    mod spans {
+        use super::*;
+        use std::thread;
+
+        fn located(input: &str) -> Result<Expr, ThbcError> {
+            Parser::parse(Tokenizer::new(input))
+        }
+
+        #[test]
+        fn parse_err_unbalanced_close() {
+            let res = located("(1))");
+            assert_eq!(Err(ThbcError::UnbalancedParen { span: span(3, 4, 1, 4) }), res);
+            assert_eq!("E0004", res.unwrap_err().code());
+        }
+
+        #[test]
+        fn parse_err_malformed_number() {
+            assert_eq!(
+                Err(ThbcError::Lex { message: String::from("Malformed number 1.2.3"), span: span(0, 5, 1, 1) }),
+                located("1.2.3")
+            );
+            assert_eq!(Ok(binop(num(".5"), '*', num("2."))), parse(".5*2."));
+        }
+
+        #[test]
+        fn parse_err_unknown_character() {
+            assert_eq!(
+                Err(ThbcError::Lex { message: String::from("Unexpected character '@'"), span: span(4, 5, 1, 5) }),
+                located("1 + @")
+            );
+        }
+
+        fn error_at(input: &str) -> (usize, usize) {
+            let span = located(input).unwrap_err().span().unwrap();
+            (span.line, span.column)
+        }
+
+        #[test]
+        fn parse_err_points_at_token() {
+            assert_eq!((1, 5), error_at("1 + * 2"));
+            assert_eq!((1, 3), error_at("1 2"));
+            assert_eq!((1, 3), error_at("(1\n  +)"));
+        }
+
+        #[test]
+        fn parse_err_end_of_input_points_past_last_token() {
+            assert_eq!(Err(ThbcError::UnexpectedEof { expected: vec!["an expression"], span: span(8, 8, 1, 9) }), located("1 + 23 *"));
+            assert_eq!((1, 1), error_at(""));
+        }
+
+        #[test]
+        fn parse_binop_has_operator_span() {
+            assert_eq!(Ok(binop(num("1"), '+', num("2")).at(span(2, 3, 1, 3))), located("1 +  2"));
+        }
+
+        #[test]
+        fn parse_nodes_have_their_spans() {
+            let value = binop(index("a", num("1")).at(span(4, 5, 1, 5)), '+', call("f", vec![num("2")]).at(span(11, 12, 1, 12)));
+            assert_eq!(Ok(assign(var("x"), value.at(span(9, 10, 1, 10))).at(span(2, 3, 1, 3))), located("x = a[1] + f(2)"));
+        }
+
+        /**
+         * Parses `input` on a thread with the stack thbc itself runs with,
+         * which a debug build needs to get anywhere near the nesting limit.
+         */
+        fn located_deep(input: String) -> Result<(), ThbcError> {
+            let parse = move || Parser::parse_program(Tokenizer::new(&input)).map(|_| ());
+            thread::Builder::new().stack_size(crate::STACK_SIZE).spawn(parse).unwrap().join().unwrap()
+        }
+
+        #[test]
+        fn parse_err_too_deep() {
+            assert_eq!(Ok(()), located_deep(format!("{}1{}", "(".repeat(255), ")".repeat(255))));
+            let res = located_deep("(".repeat(100_000));
+            assert_eq!(Err(ThbcError::TooDeep { span: span(255, 256, 1, 256) }), res);
+            assert_eq!("E0010", res.unwrap_err().code());
+            assert!(located_deep(format!("{}1", "- ".repeat(100_000))).is_err());
+            assert!(located_deep(format!("{}1", "!".repeat(100_000))).is_err());
+            assert!(located_deep(format!("{}1", "2^".repeat(100_000))).is_err());
+            assert!(located_deep(format!("{}1", "a=".repeat(100_000))).is_err());
+        }
+
+        #[test]
+        fn parse_err_too_deep_blocks() {
+            assert_eq!(Ok(()), located_deep(format!("{}1{}", "{".repeat(255), "}".repeat(255))));
+            let res = located_deep("{".repeat(100_000));
+            assert_eq!(Err(ThbcError::TooDeep { span: span(256, 257, 1, 257) }), res);
+            assert!(located_deep(format!("{}1", "if (1) ".repeat(100_000))).is_err());
+            assert!(located_deep(format!("{}1", "while (1) ".repeat(100_000))).is_err());
+        }
+    }
+}
+
+/**
+ * Internal-only parser methods to process the grammar via recursive descent.
+ */
+impl<'tokens> Parser<'tokens> {
+    // Program  -> (Sep | Define | Stmt)*
+    // StmtList -> (Sep | Stmt)*    Sep -> ';' | '\n'
+    /**
+     * Statements must be separated by a semicolon or a newline, and any
+     * number of separators may come before, between or after them. The list
+     * ends at the end of the input or at a '}', which is left to the caller.
+     * Like in bc, functions are only defined at the top level of the input,
+     * never inside of a block, a body or another function.
+     */
+    fn stmt_list(&mut self, top_level: bool) -> Result<Vec<Stmt>, ThbcError> {
+        let mut stmts = Vec::new();
+        loop {
+            match self.peek_token() {
+                Some(Token::Semicolon) | Some(Token::Newline) => {
+                    self.take_next_token()?; //skips empty statements
+                    continue;
+                }
+                Some(Token::RBrace) => break,
+                _ => {}
+            }
+            if self.at_end() {
+                break;
+            }
+            match self.peek_token() {
+                Some(Token::Keyword(Keyword::Define)) if top_level => stmts.push(self.define()?),
+                _ => stmts.push(self.stmt()?),
+            }
+            self.end_stmt()?;
+        }
+        Ok(stmts)
+    }
+
+    // Stmt     -> '{' StmtList '}' | If | While | For | 'break' | 'continue'
+    //           | Return | Print | Str | Expr
+    fn stmt(&mut self) -> Result<Stmt, ThbcError> {
+        match self.peek_token() {
+            Some(Token::LBrace) => {
+                self.take_next_token()?; //takes in the {
+                let stmts = self.nested(|parser| parser.stmt_list(false))?;
+                self.consume_token(Token::RBrace)?;
+                Ok(Stmt::Block(stmts))
+            }
+            Some(Token::Keyword(Keyword::If)) => self.if_stmt(),
+            Some(Token::Keyword(Keyword::While)) => self.while_stmt(),
+            Some(Token::Keyword(Keyword::For)) => self.for_stmt(),
+            Some(Token::Keyword(Keyword::Break)) => self.loop_control(Stmt::Break, "break"),
+            Some(Token::Keyword(Keyword::Continue)) => self.loop_control(Stmt::Continue, "continue"),
+            Some(Token::Keyword(Keyword::Define)) => {
+                let found = self.take_next_token()?; //only the top level can define functions
+                Err(self.unexpected(found, vec!["a statement"]))
+            }
+            Some(Token::Keyword(Keyword::Return)) => self.return_stmt(),
+            Some(Token::Keyword(Keyword::Print)) => self.print_stmt(),
+            Some(Token::Str(_)) => Ok(Stmt::Print(vec![self.print_item(false)?])),
+            _ => Ok(Stmt::Expr(self.expr()?)),
+        }
+    }
+
+    // If       -> 'if' '(' Expr ')' '\n'* Stmt ('else' '\n'* Stmt)?
+    /**
+     * Like GNU bc, newlines may come between the condition and the statement
+     * and after the else, but the else must be on the line its if ends on,
+     * as in `} else {`.
+     */
+    fn if_stmt(&mut self) -> Result<Stmt, ThbcError> {
+        self.take_next_token()?; //takes in the if
+        self.consume_token(Token::LParen)?;
+        let cond = self.expr()?;
+        self.consume_token(Token::RParen)?;
+        self.skip_newlines()?;
+        let then = Box::new(self.nested(Parser::stmt)?);
+        let otherwise = if let Some(Token::Keyword(Keyword::Else)) = self.peek_token() {
+            self.take_next_token()?; //takes in the else
+            self.skip_newlines()?;
+            Some(Box::new(self.nested(Parser::stmt)?))
+        } else {
+            None
+        };
+        Ok(Stmt::If { cond, then, otherwise })
+    }
+
+    // While    -> 'while' '(' Expr ')' '\n'* Stmt
+    fn while_stmt(&mut self) -> Result<Stmt, ThbcError> {
+        self.take_next_token()?; //takes in the while
+        let span = self.span;
+        self.consume_token(Token::LParen)?;
+        let cond = self.expr()?;
+        self.consume_token(Token::RParen)?;
+        let body = Box::new(self.loop_body()?);
+        Ok(Stmt::While { cond, body, span })
+    }
+
+    // For      -> 'for' '(' Expr? ';' Expr? ';' Expr? ')' '\n'* Stmt
+    fn for_stmt(&mut self) -> Result<Stmt, ThbcError> {
+        self.take_next_token()?; //takes in the for
+        let span = self.span;
+        self.consume_token(Token::LParen)?;
+        let init = self.optional_expr(Token::Semicolon)?;
+        self.consume_token(Token::Semicolon)?;
+        let cond = self.optional_expr(Token::Semicolon)?;
+        self.consume_token(Token::Semicolon)?;
+        let step = self.optional_expr(Token::RParen)?;
+        self.consume_token(Token::RParen)?;
+        let body = Box::new(self.loop_body()?);
+        Ok(Stmt::For { init, cond, step, body, span })
+    }
+
+    /**
+     * An expression of a for loop, which is left out when the token that
+     * ends it comes right away.
+     */
+    fn optional_expr(&mut self, end: Token) -> Result<Option<Expr>, ThbcError> {
+        if self.peek_token() == Some(&end) {
+            Ok(None)
+        } else {
+            Ok(Some(self.expr()?))
+        }
+    }
+
+    fn loop_body(&mut self) -> Result<Stmt, ThbcError> {
+        self.skip_newlines()?;
+        self.loops += 1;
+        let body = self.nested(Parser::stmt);
+        self.loops -= 1;
+        body
+    }
+
+    /**
+     * A break or continue, which only make sense inside of a loop.
+     */
+    fn loop_control(&mut self, stmt: Stmt, keyword: &'static str) -> Result<Stmt, ThbcError> {
+        self.take_next_token()?; //takes in the keyword
+        if self.loops == 0 {
+            return Err(ThbcError::OutsideLoop { keyword, span: self.span });
+        }
+        Ok(stmt)
+    }
+
+    // Define   -> 'define' Ident '(' Params? ')' '\n'* '{' Sep* ('auto' Params Sep)? StmtList '}'
+    /**
+     * Loops outside of the function don't surround its body, so a break in
+     * it must be inside of a loop of its own.
+     */
+    fn define(&mut self) -> Result<Stmt, ThbcError> {
+        self.take_next_token()?; //takes in the define
+        let name = self.name()?;
+        self.consume_token(Token::LParen)?;
+        let params = if let Some(Token::RParen) = self.peek_token() {
+            Vec::new()
+        } else {
+            self.params()?
+        };
+        self.consume_token(Token::RParen)?;
+        self.skip_newlines()?;
+        self.consume_token(Token::LBrace)?;
+        while let Some(Token::Newline) | Some(Token::Semicolon) = self.peek_token() {
+            self.take_next_token()?;
+        }
+        let autos = if let Some(Token::Keyword(Keyword::Auto)) = self.peek_token() {
+            self.take_next_token()?; //takes in the auto
+            let autos = self.params()?;
+            self.end_stmt()?;
+            autos
+        } else {
+            Vec::new()
+        };
+        let outer = (self.loops, self.in_function);
+        self.loops = 0;
+        self.in_function = true;
+        let body = self.stmt_list(false);
+        self.loops = outer.0;
+        self.in_function = outer.1;
+        let body = body?;
+        self.consume_token(Token::RBrace)?;
+        Ok(Stmt::Define(Rc::new(Function { name, params, autos, body })))
+    }
+
+    // Params   -> Param (',' Param)*
+    fn params(&mut self) -> Result<Vec<Param>, ThbcError> {
+        let mut params = vec![self.param()?];
+        while let Some(Token::Comma) = self.peek_token() {
+            self.take_next_token()?; //takes in the ,
+            params.push(self.param()?);
+        }
+        Ok(params)
+    }
+
+    // Param    -> Ident ('[' ']')?
+    fn param(&mut self) -> Result<Param, ThbcError> {
+        let name = self.name()?;
+        if let Some(Token::LBracket) = self.peek_token() {
+            self.take_next_token()?; //takes in the [
+            self.consume_token(Token::RBracket)?;
+            Ok(Param::Array(name))
+        } else {
+            Ok(Param::Var(name))
+        }
+    }
+
+    fn name(&mut self) -> Result<String, ThbcError> {
+        match self.take_expecting(vec!["a name"])? {
+            Token::Ident(name) => Ok(name),
+            found => Err(self.unexpected(found, vec!["a name"])),
+        }
+    }
+
+    // Return   -> 'return' Expr?
+    /**
+     * Like GNU bc, the value doesn't need to be in parentheses. A return
+     * without one returns 0.
+     */
+    fn return_stmt(&mut self) -> Result<Stmt, ThbcError> {
+        self.take_next_token()?; //takes in the return
+        if !self.in_function {
+            return Err(ThbcError::OutsideFunction { keyword: "return", span: self.span });
+        }
+        match self.peek_token() {
+            Some(Token::Semicolon) | Some(Token::Newline) | Some(Token::RBrace) | None => Ok(Stmt::Return(None)),
+            _ => Ok(Stmt::Return(Some(self.expr()?))),
+        }
+    }
+
+    // Print    -> 'print' PrintItem (',' PrintItem)*
+    fn print_stmt(&mut self) -> Result<Stmt, ThbcError> {
+        self.take_next_token()?; //takes in the print
+        let mut items = vec![self.print_item(true)?];
+        while let Some(Token::Comma) = self.peek_token() {
+            self.take_next_token()?; //takes in the ,
+            items.push(self.print_item(true)?);
+        }
+        Ok(Stmt::Print(items))
+    }
+
+    // PrintItem -> Str | Expr
+    /**
+     * Like GNU bc, only the strings of a print have their escapes replaced,
+     * so a string statement such as `"a\nb"` prints its backslash.
+     */
+    fn print_item(&mut self, escapes: bool) -> Result<PrintItem, ThbcError> {
+        match self.peek_token() {
+            Some(Token::Str(text)) => {
+                let text = text.clone();
+                self.take_next_token()?; //takes in the string
+                if !escapes {
+                    return Ok(PrintItem::Str(text));
+                }
+                match unescape(&text) {
+                    Ok(text) => Ok(PrintItem::Str(text)),
+                    Err(message) => Err(ThbcError::Lex { message, span: self.span }),
+                }
+            }
+            _ => Ok(PrintItem::Expr(self.expr()?)),
         }
+    }
 
+    fn skip_newlines(&mut self) -> Result<(), ThbcError> {
+        while let Some(Token::Newline) = self.peek_token() {
+            self.take_next_token()?;
+        }
+        Ok(())
     }
 
-    mod lvl3 {
-        use super::*;
+    /**
+     * Checks that a statement is followed by a separator, a '}' or the end
+     * of the input, without taking it.
+     */
+    fn end_stmt(&mut self) -> Result<(), ThbcError> {
+        match self.peek_token() {
+            Some(Token::Semicolon) | Some(Token::Newline) | Some(Token::RBrace) => return Ok(()),
+            _ => {}
+        }
+        if self.at_end() {
+            return Ok(());
+        }
+        match self.take_next_token()? {
+            Token::RParen => Err(ThbcError::UnbalancedParen { span: self.span }),
+            found => Err(self.unexpected(found, vec!["an operator", "';'", "a newline"])),
+        }
+    }
 
-        #[test]
-        fn parse_add() {
-            let res = Parser::parse(Tokenizer::new("1+2")).unwrap();
-            assert_eq!(binop(num(1.0), '+', num(2.0)), res);
+    // Level 0
+    // Expr     -> MaybeOr
+    fn expr(&mut self) -> Result<Expr, ThbcError> {
+        if !self.at_end() { //looks to see if there is a token in the input
+            self.nested(Parser::maybe_or) //if there is, jumps to maybe_or one level deeper
+        } else {
+            Err(self.end_of_input(vec!["an expression"])) //throws an error because nothing was entered into input or new expr() from atom() is empty
         }
+    }
 
-        #[test]
-        fn parse_sub() {
-            let res = Parser::parse(Tokenizer::new("1-2")).unwrap();
-            assert_eq!(binop(num(1.0), '-', num(2.0)), res);
+    // MaybeOr      -> MaybeAnd ('||' MaybeAnd)*
+    /**
+     * `||` has the lowest precedence of all, below `&&`, and both are left
+     * associative. They result in 1 or 0 and, like GNU bc, only evaluate
+     * their rhs when the lhs doesn't already decide the result.
+     */
+    fn maybe_or(&mut self) -> Result<Expr, ThbcError> {
+        let mut lhs = self.maybe_and()?;
+        while let Some(Token::Or) = self.peek_token() {
+            self.take_next_token()?; //takes in the ||
+            lhs = or(lhs, self.maybe_and()?);
         }
+        Ok(lhs)
+    }
 
-        #[test]
-        fn parse_multiple_add_or_sub() {
-            let res = Parser::parse(Tokenizer::new("1+2-3+4-5")).unwrap();
-            assert_eq!(binop(binop(binop(binop(num(1.0), '+', num(2.0)), '-', num(3.0)), '+', num(4.0)), '-', num(5.0)), res);
+    // MaybeAnd     -> MaybeNot ('&&' MaybeNot)*
+    fn maybe_and(&mut self) -> Result<Expr, ThbcError> {
+        let mut lhs = self.maybe_not()?;
+        while let Some(Token::And) = self.peek_token() {
+            self.take_next_token()?; //takes in the &&
+            lhs = and(lhs, self.maybe_not()?);
         }
+        Ok(lhs)
+    }
 
-        #[test]
-        fn parse_add_and_mult() {
-            let res = Parser::parse(Tokenizer::new("1+2*3")).unwrap();
-            assert_eq!(binop(num(1.0), '+', binop(num(2.0), '*', num(3.0))), res);
+    // MaybeNot     -> '!' MaybeNot | MaybeRel
+    /**
+     * `!` binds looser than the relational operators, so `!a < b` is
+     * `!(a < b)`.
+     */
+    fn maybe_not(&mut self) -> Result<Expr, ThbcError> {
+        if let Some(Token::Not) = self.peek_token() {
+            self.take_next_token()?; //takes in the !
+            Ok(not(self.nested(Parser::maybe_not)?))
+        } else {
+            self.maybe_rel()
         }
+    }
 
-        #[test]
-        fn parse_stuff() {
-            let res = Parser::parse(Tokenizer::new("(1+2)/(4-3)")).unwrap();
-            assert_eq!(binop(binop(num(1.0), '+', num(2.0)), '/', binop(num(4.0), '-', num(3.0))), res);
+    // MaybeRel     -> MaybeAssign (RelOp MaybeAssign)*
+    /**
+     * Like GNU bc, relational operators bind looser than assignment, so
+     * `a = 1 < 2` compares the result of `a = 1` with 2. They are left
+     * associative and produce 1 when the relation holds and 0 otherwise.
+     */
+    fn maybe_rel(&mut self) -> Result<Expr, ThbcError> {
+        let mut lhs = self.maybe_assign()?;
+        while let Some(Token::RelOp(op)) = self.peek_token() {
+            let op = *op;
+            self.take_next_token()?; //takes in the relational operator
+            let rhs = self.maybe_assign()?;
+            lhs = rel(lhs, op, rhs); //grows the tree down the lhs
         }
-        
+        Ok(lhs)
     }
-    // TODO: Add tests for lvl > 0
-}
 
-/**
- * Internal-only parser methods to process the grammar via recursive descent.
- */
-impl<'tokens> Parser<'tokens> {
-    // Level 0
-    // Expr     -> Atom
-    fn expr(&mut self) -> Result<Expr, String> {
-        if let Some(token) = self.tokens.peek() { //looks to see if there is a token in the input
-            self.maybe_add_sub() //if there is, jumps to maybe_add_sub
-        } else {
-            Err(format!("Unexpected end of input")) //throws an error because nothing was entered into input or new expr() from atom() is empty
+    // MaybeAssign  -> MaybeAddSub (AssignOp MaybeAssign)?
+    // AssignOp     -> '=' | '+=' | '-=' | '*=' | '/=' | '%=' | '^='
+    /**
+     * Assignment is right associative, so `a = b = 1` stores 1 in both.
+     * Only a variable, array element or special variable may appear on the
+     * lhs.
+     */
+    fn maybe_assign(&mut self) -> Result<Expr, ThbcError> {
+        let start = self.next_start();
+        let lhs = self.maybe_add_sub()?;
+        let target = self.source_since(start);
+        let op = match self.peek_token() {
+            Some(Token::Assignment) => None,
+            Some(Token::CompoundAssignment(op)) => Some(*op),
+            _ => return Ok(lhs),
+        };
+        self.take_next_token()?; //takes in the assignment operator
+        let span = self.span;
+        let lhs = lhs.assignable(span, target)?;
+        let value = self.nested(Parser::maybe_assign)?;
+        Ok(match op {
+            None => assign(lhs, value),
+            Some(op) => op_assign(lhs, op, value),
         }
+        .at(span))
     }
 
-    // Atom     -> '(' Expr ')' | Num
-    fn atom(&mut self) -> Result<Expr, String> {
-        let next = self.take_next_token(); //takes in the next token
+    // Atom     -> '(' Expr ')' | Num | Ident | Ident CallArgs | Ident Subscript | Special
+    //           | Builtin '(' Expr ')' | '!' MaybeNot
+    /**
+     * A `!` may also start an operand, as in `1 + !0`. It still covers
+     * everything that binds tighter than itself, so `1 + !0 + 1` is
+     * `1 + !(0 + 1)` just like in GNU bc.
+     */
+    fn atom(&mut self) -> Result<Expr, ThbcError> {
+        let next = self.take_expecting(vec!["an expression"]); //takes in the next token
         match next {
             Ok(Token::LParen) => {
+                let open = self.span; //an unclosed paren is reported where it was opened
                 let expr = self.expr()?; //if the next token is a LParen, creates a new Expr
-                let right_paren = self.consume_token(Token::RParen)?;
+                self.close_paren(open)?;
                 Ok(expr) //returns the expr inside of the parenthesis
             },
-            Ok(Token::Number(c)) => Ok(num(c)), //if its just a number it returns that number as the atom
-            _ => Err(format!("Unexpected end of input")) //returns an error becomes something is missing
+            Ok(Token::Number(c)) => Ok(Expr::Num(c)), //if its just a number it returns that number as the atom
+            Ok(Token::Ident(name)) => {
+                let span = self.span;
+                match self.peek_token() {
+                    Some(Token::LParen) => Ok(call(&name, self.call_args()?).at(span)), //a name followed by ( calls the function
+                    Some(Token::LBracket) => Ok(index(&name, self.subscript()?).at(span)), //and followed by [ reads an element
+                    _ => Ok(Expr::Var(name)), //an identifier reads the variable's value
+                }
+            },
+            Ok(Token::Keyword(Keyword::Ibase)) => Ok(special(Special::Ibase)),
+            Ok(Token::Keyword(Keyword::Obase)) => Ok(special(Special::Obase)),
+            Ok(Token::Keyword(Keyword::Scale)) => {
+                if let Some(Token::LParen) = self.peek_token() { //scale(x) is the builtin, scale alone the variable
+                    Ok(builtin(Builtin::Scale, self.call_arg()?))
+                } else {
+                    Ok(special(Special::Scale))
+                }
+            },
+            Ok(Token::Not) => Ok(not(self.nested(Parser::maybe_not)?)),
+            Ok(token) => Err(self.unexpected(token, vec!["an expression"])), //anything else can't start an atom
+            Err(e) => Err(e), //returns an error becomes something is missing
+        }
+    }
+
+    // Unary    -> ('-'|'+') Unary | ('++'|'--') Atom | Atom ('++'|'--')?
+    /**
+     * Like bc, a prefix sign binds tighter than `^`, so `-2^2` is `(-2)^2`.
+     * Unary plus has no effect and does not produce a node. `++` and `--`
+     * bind tighter still, so `-x++` negates the old value of x; like
+     * assignments they only apply to what can be assigned.
+     */
+    fn unary(&mut self) -> Result<Expr, ThbcError> {
+        match self.peek_operator() {
+            Some('-') => {
+                self.take_operator()?; //takes in the -
+                Ok(neg(self.nested(Parser::unary)?)) //signs can be stacked, so recur
+            },
+            Some('+') => {
+                self.take_operator()?; //takes in the +
+                self.nested(Parser::unary)
+            },
+            _ => {
+                if let Some(op) = self.peek_inc_dec() {
+                    self.take_next_token()?; //takes in the ++ or --
+                    let span = self.span;
+                    let start = self.next_start();
+                    let target = self.atom()?;
+                    return Ok(pre(op, target.assignable(span, self.source_since(start))?).at(span));
+                }
+                let atom = self.atom()?;
+                match self.peek_inc_dec() {
+                    Some(op) if atom.is_assignable() => {
+                        self.take_next_token()?; //takes in the ++ or --
+                        Ok(post(atom, op).at(self.span))
+                    },
+                    _ => Ok(atom),
+                }
+            },
+        }
+    }
+
+    /**
+     * The operator applied by a `++` or `--` coming up next, if there is one.
+     */
+    fn peek_inc_dec(&mut self) -> Option<char> {
+        match self.peek_token() {
+            Some(Token::Increment) => Some('+'),
+            Some(Token::Decrement) => Some('-'),
+            _ => None,
+        }
+    }
+
+    // CallArg  -> '(' Expr ')'
+    fn call_arg(&mut self) -> Result<Expr, ThbcError> {
+        self.consume_token(Token::LParen)?;
+        let open = self.span;
+        let arg = self.expr()?;
+        self.close_paren(open)?;
+        Ok(arg)
+    }
+
+    // CallArgs -> '(' (Arg (',' Arg)*)? ')'     Arg -> Ident '[' ']' | Expr
+    fn call_args(&mut self) -> Result<Vec<Expr>, ThbcError> {
+        self.consume_token(Token::LParen)?;
+        let open = self.span;
+        let mut args = Vec::new();
+        if let Some(Token::RParen) = self.peek_token() {
+            self.take_next_token()?; //takes in the )
+            return Ok(args);
+        }
+        loop {
+            if self.at_array_arg() {
+                args.push(Expr::Array(self.name()?));
+                self.take_next_token()?; //takes in the [
+                self.take_next_token()?; //takes in the ]
+            } else {
+                args.push(self.expr()?);
+            }
+            match self.take_next_token() {
+                Ok(Token::Comma) => {}
+                Ok(Token::RParen) => return Ok(args),
+                Ok(token) => return Err(self.unexpected(token, vec!["','", "')'"])),
+                Err(ThbcError::UnexpectedEof { .. }) => return Err(ThbcError::UnbalancedParen { span: open }),
+                Err(e) => return Err(e),
+            }
         }
     }
 
+    /**
+     * Looks past the next token for a whole array `a[]` without taking
+     * anything, since `a[` may just as well start an element.
+     */
+    fn at_array_arg(&self) -> bool {
+        let mut ahead = self.tokens.clone().map(|item| item.map(|(token, _)| token));
+        matches!(ahead.next(), Some(Ok(Token::Ident(_))))
+            && matches!(ahead.next(), Some(Ok(Token::LBracket)))
+            && matches!(ahead.next(), Some(Ok(Token::RBracket)))
+    }
+
+    // Subscript -> '[' Expr ']'
+    fn subscript(&mut self) -> Result<Expr, ThbcError> {
+        self.consume_token(Token::LBracket)?;
+        let index = self.expr()?;
+        self.consume_token(Token::RBracket)?;
+        Ok(index)
+    }
+
     // Level 1:
-    // MaybeMulDiv  -> Atom MulDivOp?
-    fn maybe_mul_div(&mut self) -> Result<Expr, String> {
-        let lhs = self.atom()?; //takes in the lhs argument of the input
+    // MaybePow     -> Unary ('^' MaybePow)?
+    /**
+     * Exponentiation binds tighter than `*` and `/` and is right associative,
+     * so `2^3^2` is `2^(3^2)`. The recursion on the rhs takes care of that.
+     */
+    fn maybe_pow(&mut self) -> Result<Expr, ThbcError> {
+        let lhs = self.unary()?; //takes in the base
+        if let Some('^') = self.peek_operator() {
+            let op = self.take_operator()?; //takes in the ^
+            let span = self.span;
+            let rhs = self.nested(Parser::maybe_pow)?; //recurs so that a^b^c groups to the right
+            Ok(binop(lhs, op, rhs).at(span))
+        } else {
+            Ok(lhs) //just returns the base if there is no ^
+        }
+    }
+
+    // Level 2:
+    // MaybeMulDiv  -> MaybePow MulDivOp?
+    fn maybe_mul_div(&mut self) -> Result<Expr, ThbcError> {
+        let lhs = self.maybe_pow()?; //takes in the lhs argument of the input
         let oper = self.peek_operator();
         if let Some(op) = oper { //looks to see if there is an operator
             match oper.unwrap() {
-                '*'|'/' => self.mul_div_op(lhs), //jumps to mul_div_op if operator is * or /
+                '*'|'/'|'%' => self.mul_div_op(lhs), //jumps to mul_div_op if operator is *, / or %
                 _ => Ok(lhs),
             }
         } else {
@@ -220,18 +1965,18 @@ impl<'tokens> Parser<'tokens> {
         }
     }
 
-    // MulDivOp     -> ('*'|'/') Atom
+    // MulDivOp     -> ('*'|'/'|'%') MaybePow MulDivOp?
     /**
      * The lhs: Expr is passed in so that the syntax tree can grow "down" the lhs.
      */
-    fn mul_div_op(&mut self, lhs: Expr) -> Result<Expr, String> {
+    fn mul_div_op(&mut self, lhs: Expr) -> Result<Expr, ThbcError> {
         let op = self.take_operator()?; //takes in the operator after lhs
-        let rhs = self.atom()?; //calls atom to find the rhs
-        let bin = binop(lhs, op, rhs); //creates a binop with lhs, op, and rhs
-        if let Some(operator) = self.peek_operator() { //checks to see if there is another op after rhs
-            self.mul_div_op(bin) //calls mull_div_op if this is the case
-        } else {
-            Ok(bin) //returns the binop if the input is over
+        let span = self.span; //remembers where the operator is for errors
+        let rhs = self.maybe_pow()?; //calls maybe_pow to find the rhs
+        let bin = binop(lhs, op, rhs).at(span); //creates a binop with lhs, op, and rhs
+        match self.peek_operator() { //checks to see if there is another *, / or % after rhs
+            Some('*') | Some('/') | Some('%') => self.mul_div_op(bin), //calls mull_div_op if this is the case
+            _ => Ok(bin), //returns the binop, lower precedence operators are left to the caller
         }
     }
 
@@ -239,7 +1984,7 @@ impl<'tokens> Parser<'tokens> {
     // MaybeAddSub -> MaybeMulDiv AddSubOp?
     // AddSubOp    -> ('+'|'-') MaybeMulDiv AddSubOp?
     
-    fn maybe_add_sub(&mut self) -> Result<Expr, String> {
+    fn maybe_add_sub(&mut self) -> Result<Expr, ThbcError> {
         let lhs = self.maybe_mul_div()?; //takes either a multiplication or division binop or single number
         let oper = self.peek_operator(); //checks to see if there is an operator
         if let Some(op) = oper {
@@ -252,14 +1997,14 @@ impl<'tokens> Parser<'tokens> {
         }
     }
 
-    fn add_sub_op(&mut self, lhs: Expr) -> Result<Expr, String> {
+    fn add_sub_op(&mut self, lhs: Expr) -> Result<Expr, ThbcError> {
         let op = self.take_operator()?; //takes in the + or -
+        let span = self.span;
         let rhs = self.maybe_mul_div()?; //either returns another binop of * or / or just a number on the other side of the + or -
-        let bin = binop(lhs, op, rhs); //creates the binop
-        if let Some(operator) = self.peek_operator() { 
-            self.add_sub_op(bin) //if the operator exists then we call add_sub_op again since it must be this, since we already ran maybe_mul_div
-        } else {
-            Ok(bin) //returns the binop if there is no other operator
+        let bin = binop(lhs, op, rhs).at(span); //creates the binop
+        match self.peek_operator() {
+            Some('+') | Some('-') => self.add_sub_op(bin), //another + or - continues the chain to the left
+            _ => Ok(bin), //returns the binop if there is no other operator
         }
     }
 
@@ -274,36 +2019,36 @@ mod private_api {
 
         #[test]
         fn atom_ok() {
-            assert_eq!(Parser::from("1").atom().unwrap(), num(1.0));
-            assert_eq!(Parser::from("(1)").atom().unwrap(), num(1.0));
-            assert_eq!(Parser::from("((1))").atom().unwrap(), num(1.0));
+            assert_eq!(Parser::from("1").atom().without_spans().unwrap(), num("1"));
+            assert_eq!(Parser::from("(1)").atom().without_spans().unwrap(), num("1"));
+            assert_eq!(Parser::from("((1))").atom().without_spans().unwrap(), num("1"));
         }
 
         #[test]
         fn atom_err_empty_parens() {
             assert_eq!(
-                Parser::from("()").atom(),
-                Err(String::from("Unexpected token: RParen")),
+                Parser::from("()").atom().without_spans(),
+                Err(ThbcError::UnexpectedToken { found: Token::RParen, expected: vec!["an expression"], span: Span::default() }),
             );
         }
 
         #[test]
         fn atom_err_not_an_atom() {
             assert_eq!(
-                Parser::from("+").atom(),
-                Err(String::from("Unexpected token: Operator('+')")),
+                Parser::from("+").atom().without_spans(),
+                Err(ThbcError::UnexpectedToken { found: Token::Operator('+'), expected: vec!["an expression"], span: Span::default() }),
             );
         }
 
         #[test]
         fn atom_err_incomplete() {
             assert_eq!(
-                Parser::from("(").atom(),
-                Err(String::from("Unexpected end of input"))
+                Parser::from("(").atom().without_spans(),
+                Err(ThbcError::UnexpectedEof { expected: vec!["an expression"], span: Span::default() })
             );
             assert_eq!(
-                Parser::from("(1").atom(),
-                Err(String::from("Unexpected end of input"))
+                Parser::from("(1").atom().without_spans(),
+                Err(ThbcError::UnbalancedParen { span: Span::default() })
             );
         }
     }
@@ -313,30 +2058,30 @@ mod private_api {
 
         #[test]
         fn maybe_mul_div_atom() {
-            assert_eq!(Parser::from("1").maybe_mul_div().unwrap(), num(1.0));
+            assert_eq!(Parser::from("1").maybe_mul_div().without_spans().unwrap(), num("1"));
         }
 
         #[test]
         fn maybe_mul_div() {
             assert_eq!(
-                Parser::from("1*2").maybe_mul_div().unwrap(),
-                binop(num(1.0), '*', num(2.0))
+                Parser::from("1*2").maybe_mul_div().without_spans().unwrap(),
+                binop(num("1"), '*', num("2"))
             );
             assert_eq!(
-                Parser::from("1/2").maybe_mul_div().unwrap(),
-                binop(num(1.0), '/', num(2.0))
+                Parser::from("1/2").maybe_mul_div().without_spans().unwrap(),
+                binop(num("1"), '/', num("2"))
             );
         }
 
         #[test]
         fn mul_div_op() {
             assert_eq!(
-                Parser::from("*2").mul_div_op(num(1.0)).unwrap(),
-                binop(num(1.0), '*', num(2.0))
+                Parser::from("*2").mul_div_op(num("1")).without_spans().unwrap(),
+                binop(num("1"), '*', num("2"))
             );
             assert_eq!(
-                Parser::from("/2").mul_div_op(num(1.0)).unwrap(),
-                binop(num(1.0), '/', num(2.0))
+                Parser::from("/2").mul_div_op(num("1")).without_spans().unwrap(),
+                binop(num("1"), '/', num("2"))
             );
         }
     }
@@ -344,53 +2089,61 @@ mod private_api {
     mod lvl2 {
         use super::*;
 
+        #[test]
+        fn maybe_mul_div_modulo() {
+            assert_eq!(
+                Parser::from("5%2^2").maybe_mul_div().without_spans().unwrap(),
+                binop(num("5"), '%', binop(num("2"), '^', num("2")))
+            );
+        }
+
         #[test]
         fn maybe_mul_div_division() {
             assert_eq!(
-                Parser::from("1/2/3").maybe_mul_div().unwrap(),
-                binop(binop(num(1.0), '/', num(2.0)), '/', num(3.0))
+                Parser::from("1/2/3").maybe_mul_div().without_spans().unwrap(),
+                binop(binop(num("1"), '/', num("2")), '/', num("3"))
             );
         }
 
         #[test]
         fn mul_div_op_multiplication() {
             assert_eq!(
-                Parser::from("*2*3").mul_div_op(num(1.0)).unwrap(),
-                binop(binop(num(1.0), '*', num(2.0)), '*', num(3.0))
+                Parser::from("*2*3").mul_div_op(num("1")).without_spans().unwrap(),
+                binop(binop(num("1"), '*', num("2")), '*', num("3"))
             );
             assert_eq!(
                 Parser::from("*3")
-                    .mul_div_op(binop(num(1.0), '*', num(2.0)))
+                    .mul_div_op(binop(num("1"), '*', num("2"))).without_spans()
                     .unwrap(),
-                binop(binop(num(1.0), '*', num(2.0)), '*', num(3.0))
+                binop(binop(num("1"), '*', num("2")), '*', num("3"))
             );
         }
 
         #[test]
         fn maybe_mul_div_multiplication() {
             assert_eq!(
-                Parser::from("1*2*3").maybe_mul_div().unwrap(),
-                binop(binop(num(1.0), '*', num(2.0)), '*', num(3.0))
+                Parser::from("1*2*3").maybe_mul_div().without_spans().unwrap(),
+                binop(binop(num("1"), '*', num("2")), '*', num("3"))
             );
         }
 
         #[test]
         fn mul_div_op_division() {
             assert_eq!(
-                Parser::from("/2/3").mul_div_op(num(1.0)).unwrap(),
-                binop(binop(num(1.0), '/', num(2.0)), '/', num(3.0))
+                Parser::from("/2/3").mul_div_op(num("1")).without_spans().unwrap(),
+                binop(binop(num("1"), '/', num("2")), '/', num("3"))
             );
             assert_eq!(
                 Parser::from("/3")
-                    .mul_div_op(binop(num(1.0), '/', num(2.0)))
+                    .mul_div_op(binop(num("1"), '/', num("2"))).without_spans()
                     .unwrap(),
-                binop(binop(num(1.0), '/', num(2.0)), '/', num(3.0))
+                binop(binop(num("1"), '/', num("2")), '/', num("3"))
             );
         }
 
         #[test]
         fn maybe_mul_div_atom() {
-            assert_eq!(Parser::from("1").maybe_mul_div().unwrap(), num(1.0));
+            assert_eq!(Parser::from("1").maybe_mul_div().without_spans().unwrap(), num("1"));
         }
 
     }
@@ -401,75 +2154,278 @@ mod private_api {
         #[test]
         fn maybe_add_sub_with_mult() {
             assert_eq!(
-                Parser::from("1*2*3").maybe_add_sub().unwrap(),
-                binop(binop(num(1.0), '*', num(2.0)), '*', num(3.0))
+                Parser::from("1*2*3").maybe_add_sub().without_spans().unwrap(),
+                binop(binop(num("1"), '*', num("2")), '*', num("3"))
             );
         }
 
         #[test]
         fn maybe_add_sub_with_add_and_sub() {
             assert_eq!(
-                Parser::from("1+2-3").maybe_add_sub().unwrap(),
-                binop(binop(num(1.0), '+', num(2.0)), '-', num(3.0))
+                Parser::from("1+2-3").maybe_add_sub().without_spans().unwrap(),
+                binop(binop(num("1"), '+', num("2")), '-', num("3"))
             );
         }
 
         #[test]
         fn maybe_add_sub_with_both() {
             assert_eq!(
-                Parser::from("1+2*3").maybe_add_sub().unwrap(),
-                binop(num(1.0), '+', binop(num(2.0), '*', num(3.0)))
+                Parser::from("1+2*3").maybe_add_sub().without_spans().unwrap(),
+                binop(num("1"), '+', binop(num("2"), '*', num("3")))
             );
         }
         
         #[test]
         fn add_sub_op_with_div() {
             assert_eq!(
-                Parser::from("/2/3").mul_div_op(num(1.0)).unwrap(),
-                binop(binop(num(1.0), '/', num(2.0)), '/', num(3.0))
+                Parser::from("/2/3").mul_div_op(num("1")).without_spans().unwrap(),
+                binop(binop(num("1"), '/', num("2")), '/', num("3"))
             );
             assert_eq!(
                 Parser::from("/3")
-                    .mul_div_op(binop(num(1.0), '/', num(2.0)))
+                    .mul_div_op(binop(num("1"), '/', num("2"))).without_spans()
                     .unwrap(),
-                binop(binop(num(1.0), '/', num(2.0)), '/', num(3.0))
+                binop(binop(num("1"), '/', num("2")), '/', num("3"))
             );  
         }
 
+        // These two drove mul_div_op before * and / bound tighter than + and -.
+        // mul_div_op now stops at + and -, so they drive add_sub_op, the method
+        // they are named for, with the expected trees unchanged. add_sub_op
+        // only takes + and -, so the whole of 1*2+3 goes to maybe_add_sub.
         #[test]
         fn add_sub_op_with_sub() {
             assert_eq!(
-                Parser::from("-2-3").mul_div_op(num(1.0)).unwrap(),
-                binop(binop(num(1.0), '-', num(2.0)), '-', num(3.0))
+                Parser::from("-2-3").add_sub_op(num("1")).without_spans().unwrap(),
+                binop(binop(num("1"), '-', num("2")), '-', num("3"))
             );
             assert_eq!(
                 Parser::from("-3")
-                    .mul_div_op(binop(num(1.0), '-', num(2.0)))
+                    .add_sub_op(binop(num("1"), '-', num("2"))).without_spans()
                     .unwrap(),
-                binop(binop(num(1.0), '-', num(2.0)), '-', num(3.0))
+                binop(binop(num("1"), '-', num("2")), '-', num("3"))
             );
         }
 
         #[test]
         fn add_sub_op_with_both() {
             assert_eq!(
-                Parser::from("*2+3").mul_div_op(num(1.0)).unwrap(),
-                binop(binop(num(1.0), '*', num(2.0)), '+', num(3.0))
+                Parser::from("1*2+3").maybe_add_sub().without_spans().unwrap(),
+                binop(binop(num("1"), '*', num("2")), '+', num("3"))
             );
             assert_eq!(
                 Parser::from("+3")
-                    .mul_div_op(binop(num(1.0), '*', num(2.0)))
+                    .add_sub_op(binop(num("1"), '*', num("2"))).without_spans()
                     .unwrap(),
-                binop(binop(num(1.0), '*', num(2.0)), '+', num(3.0))
+                binop(binop(num("1"), '*', num("2")), '+', num("3"))
+            );
+        }
+
+        #[test]
+        fn add_sub_op_groups_mul_on_the_rhs() {
+            assert_eq!(
+                Parser::from("+2*3").add_sub_op(num("1")).without_spans().unwrap(),
+                binop(num("1"), '+', binop(num("2"), '*', num("3")))
+            );
+        }
+
+        #[test]
+        fn mul_div_op_stops_at_add_sub() {
+            assert_eq!(
+                Parser::from("*2-3").mul_div_op(num("1")).without_spans().unwrap(),
+                binop(num("1"), '*', num("2"))
             );
         }
 
         #[test]
         fn maybe_add_sub_with_atom() {
-            assert_eq!(Parser::from("1").maybe_add_sub().unwrap(), num(1.0));
+            assert_eq!(Parser::from("1").maybe_add_sub().without_spans().unwrap(), num("1"));
         }
         
     }
+
+    mod pow {
+        use super::*;
+
+        #[test]
+        fn maybe_pow_atom() {
+            assert_eq!(Parser::from("2").maybe_pow().without_spans().unwrap(), num("2"));
+        }
+
+        #[test]
+        fn maybe_pow_right_associative() {
+            assert_eq!(
+                Parser::from("1^2^3").maybe_pow().without_spans().unwrap(),
+                binop(num("1"), '^', binop(num("2"), '^', num("3")))
+            );
+        }
+
+        #[test]
+        fn maybe_pow_stops_at_mul() {
+            assert_eq!(
+                Parser::from("2^3*4").maybe_pow().without_spans().unwrap(),
+                binop(num("2"), '^', num("3"))
+            );
+        }
+
+        #[test]
+        fn mul_div_op_with_pow() {
+            assert_eq!(
+                Parser::from("*2^3").mul_div_op(num("1")).without_spans().unwrap(),
+                binop(num("1"), '*', binop(num("2"), '^', num("3")))
+            );
+        }
+
+        #[test]
+        fn maybe_pow_neg_exponent() {
+            assert_eq!(
+                Parser::from("2^-3").maybe_pow().without_spans().unwrap(),
+                binop(num("2"), '^', neg(num("3")))
+            );
+        }
+
+        #[test]
+        fn maybe_pow_err_missing_exponent() {
+            assert_eq!(
+                Parser::from("2^").maybe_pow().without_spans(),
+                Err(ThbcError::UnexpectedEof { expected: vec!["an expression"], span: Span::default() })
+            );
+        }
+    }
+
+    mod assign {
+        use super::*;
+
+        #[test]
+        fn atom_variable() {
+            assert_eq!(Parser::from("z").atom().without_spans().unwrap(), var("z"));
+            assert_eq!(Parser::from("total").atom().without_spans().unwrap(), var("total"));
+        }
+
+        #[test]
+        fn atom_err_keyword() {
+            assert_eq!(
+                Parser::from("while").atom().without_spans(),
+                Err(ThbcError::UnexpectedToken { found: Token::Keyword(Keyword::While), expected: vec!["an expression"], span: Span::default() })
+            );
+        }
+
+        #[test]
+        fn maybe_assign_without_assignment() {
+            assert_eq!(
+                Parser::from("a*2").maybe_assign().without_spans().unwrap(),
+                binop(var("a"), '*', num("2"))
+            );
+        }
+
+        #[test]
+        fn maybe_assign_err_missing_value() {
+            assert_eq!(
+                Parser::from("a =").maybe_assign().without_spans(),
+                Err(ThbcError::UnexpectedEof { expected: vec!["an expression"], span: Span::default() })
+            );
+        }
+    }
+
+    mod scale {
+        use super::*;
+
+        #[test]
+        fn atom_scale() {
+            assert_eq!(Parser::from("scale").atom().without_spans().unwrap(), special(Special::Scale));
+        }
+
+        #[test]
+        fn call_arg() {
+            assert_eq!(Parser::from("(1+2)").call_arg().without_spans().unwrap(), binop(num("1"), '+', num("2")));
+        }
+
+        #[test]
+        fn call_arg_err_unclosed() {
+            assert_eq!(
+                Parser::from("scale(1").atom().without_spans(),
+                Err(ThbcError::UnbalancedParen { span: Span::default() })
+            );
+        }
+    }
+
+    mod unary {
+        use super::*;
+
+        #[test]
+        fn unary_atom() {
+            assert_eq!(Parser::from("1").unary().without_spans().unwrap(), num("1"));
+        }
+
+        #[test]
+        fn unary_stacked_signs() {
+            assert_eq!(Parser::from("-+-1").unary().without_spans().unwrap(), neg(neg(num("1"))));
+        }
+
+        #[test]
+        fn unary_err_missing_operand() {
+            assert_eq!(
+                Parser::from("-").unary().without_spans(),
+                Err(ThbcError::UnexpectedEof { expected: vec!["an expression"], span: Span::default() })
+            );
+        }
+    }
+
+    mod rel {
+        use super::*;
+
+        #[test]
+        fn maybe_rel_without_relation() {
+            assert_eq!(Parser::from("x = 1").maybe_rel().without_spans().unwrap(), assign(var("x"), num("1")));
+        }
+
+        #[test]
+        fn maybe_rel_each_operator() {
+            let ops = [RelOp::Lt, RelOp::Le, RelOp::Gt, RelOp::Ge, RelOp::Eq, RelOp::Ne];
+            for (text, op) in ["<", "<=", ">", ">=", "==", "!="].iter().zip(ops.iter()) {
+                let input = format!("a {} -1", text);
+                assert_eq!(Parser::from(&input).maybe_rel().without_spans().unwrap(), rel(var("a"), *op, neg(num("1"))));
+            }
+        }
+
+        #[test]
+        fn maybe_rel_stops_at_paren() {
+            let mut parser = Parser::from("1 < 2)");
+            assert_eq!(parser.maybe_rel().unwrap(), rel(num("1"), RelOp::Lt, num("2")));
+            assert_eq!(parser.peek_token(), Some(&Token::RParen));
+        }
+    }
+
+    mod logic {
+        use super::*;
+
+        #[test]
+        fn maybe_or_chains_left() {
+            assert_eq!(Parser::from("1 || 0 || a").maybe_or().without_spans().unwrap(), or(or(num("1"), num("0")), var("a")));
+        }
+
+        #[test]
+        fn maybe_and_chains_left() {
+            assert_eq!(Parser::from("1 && 0 && a").maybe_and().without_spans().unwrap(), and(and(num("1"), num("0")), var("a")));
+        }
+
+        #[test]
+        fn maybe_and_stops_at_or() {
+            let mut parser = Parser::from("1 && 2 || 3");
+            assert_eq!(parser.maybe_and().unwrap(), and(num("1"), num("2")));
+            assert_eq!(parser.peek_token(), Some(&Token::Or));
+        }
+
+        #[test]
+        fn maybe_not_stacks() {
+            assert_eq!(Parser::from("!!x = 1").maybe_not().without_spans().unwrap(), not(not(assign(var("x"), num("1")))));
+        }
+
+        #[test]
+        fn atom_not() {
+            assert_eq!(Parser::from("!2^2").atom().without_spans().unwrap(), not(binop(num("2"), '^', num("2"))));
+        }
+    }
 }
 
 /* Parser's Helper Methods to improve ergonomics of parsing */
@@ -480,7 +2436,12 @@ impl<'tokens> Parser<'tokens> {
      */
     fn from(input: &'tokens str) -> Parser<'tokens> {
         Parser {
+            source: input,
             tokens: Tokenizer::new(input).peekable(),
+            span: Span::default(),
+            loops: 0,
+            in_function: false,
+            depth: 0,
         }
     }
 
@@ -494,12 +2455,111 @@ impl<'tokens> Parser<'tokens> {
      * Notice the ? usage will automatically propagate the Err or
      * unwrap the value of Ok.
      */
-    fn take_next_token(&mut self) -> Result<Token, String> {
-        if let Some(token) = self.tokens.next() {
-            Ok(token)
-        } else {
-            Err(String::from("Unexpected end of input"))
+    fn take_next_token(&mut self) -> Result<Token, ThbcError> {
+        self.take_expecting(vec![])
+    }
+
+    /**
+     * Like take_next_token, but running out of input is reported along with
+     * what the grammar `expected` to find there.
+     */
+    fn take_expecting(&mut self, expected: Vec<&'static str>) -> Result<Token, ThbcError> {
+        match self.tokens.next() {
+            Some(Ok((token, span))) => {
+                self.span = span;
+                Ok(token)
+            }
+            Some(Err(e)) => Err(e),
+            None => Err(self.end_of_input(expected)),
+        }
+    }
+
+    /**
+     * Peeks at the next token without its span. Input the tokenizer could
+     * not make sense of looks like no token at all, so use at_end to tell
+     * whether the input is really over; taking the token reports its error.
+     */
+    fn peek_token(&mut self) -> Option<&Token> {
+        match self.tokens.peek() {
+            Some(Ok((token, _))) => Some(token),
+            _ => None,
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.tokens.peek().is_none()
+    }
+
+    /**
+     * The offset in the input where the next token starts.
+     */
+    fn next_start(&mut self) -> usize {
+        match self.tokens.peek() {
+            Some(Ok((_, span))) => span.start,
+            _ => self.span.end,
+        }
+    }
+
+    /**
+     * The input from offset `start` up to the end of the last token taken.
+     */
+    fn source_since(&self, start: usize) -> &'tokens str {
+        &self.source[start..self.span.end]
+    }
+
+    /**
+     * The error for running out of input, which points just past the last
+     * token taken.
+     */
+    fn end_of_input(&self, expected: Vec<&'static str>) -> ThbcError {
+        let length = self.span.end - self.span.start;
+        let span = Span {
+            start: self.span.end,
+            end: self.span.end,
+            line: self.span.line.max(1),
+            column: self.span.column.max(1) + length,
+        };
+        ThbcError::UnexpectedEof { expected, span }
+    }
+
+    /**
+     * The error for finding a token other than one of those `expected`,
+     * pointing at the token that was just taken.
+     */
+    fn unexpected(&self, found: Token, expected: Vec<&'static str>) -> ThbcError {
+        ThbcError::UnexpectedToken {
+            found,
+            expected,
+            span: self.span,
+        }
+    }
+
+    /**
+     * Takes the ')' matching the '(' at `open`. Running out of input first
+     * means the '(' was never closed, so the error points back at it.
+     */
+    fn close_paren(&mut self, open: Span) -> Result<(), ThbcError> {
+        match self.take_next_token() {
+            Ok(Token::RParen) => Ok(()),
+            Ok(token) => Err(self.unexpected(token, vec!["')'"])),
+            Err(ThbcError::UnexpectedEof { .. }) => Err(ThbcError::UnbalancedParen { span: open }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /**
+     * Parses with `parse` one level deeper into the input, or fails when
+     * that would nest deeper than MAX_DEPTH, pointing at the token that
+     * opened the level.
+     */
+    fn nested<T>(&mut self, parse: fn(&mut Self) -> Result<T, ThbcError>) -> Result<T, ThbcError> {
+        if self.depth == MAX_DEPTH {
+            return Err(ThbcError::TooDeep { span: self.span });
         }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
     }
 
     /**
@@ -508,7 +2568,7 @@ impl<'tokens> Parser<'tokens> {
      * or it will return None.
      */
     fn peek_operator(&mut self) -> Option<char> {
-        if let Some(Token::Operator(op)) = self.tokens.peek() {
+        if let Some(Token::Operator(op)) = self.peek_token() {
             Some(*op)
         } else {
             None
@@ -522,12 +2582,10 @@ impl<'tokens> Parser<'tokens> {
      *
      * let op: char = self.take_operator()?;
      */
-    fn take_operator(&mut self) -> Result<char, String> {
-        let token = self.tokens.next();
-        if let Some(Token::Operator(op)) = token {
-            Ok(op)
-        } else {
-            Err(format!("Expected operator, found {:?}", token))
+    fn take_operator(&mut self) -> Result<char, ThbcError> {
+        match self.take_expecting(vec!["an operator"])? {
+            Token::Operator(op) => Ok(op),
+            token => Err(self.unexpected(token, vec!["an operator"])),
         }
     }
 
@@ -537,15 +2595,23 @@ impl<'tokens> Parser<'tokens> {
      * next token or if it is not _exactly_ the Token you expected
      * next. If it is the token you expected, it will return Ok(Token).
      */
-    fn consume_token(&mut self, expected: Token) -> Result<Token, String> {
-        if let Some(next) = self.tokens.next() {
-            if next != expected {
-                Err(format!("Expected: {:?} - Found {:?}", expected, next))
-            } else {
-                Ok(next)
-            }
+    fn consume_token(&mut self, expected: Token) -> Result<Token, ThbcError> {
+        let description = match expected {
+            Token::LParen => "'('",
+            Token::RParen => "')'",
+            Token::LBrace => "'{'",
+            Token::RBrace => "'}'",
+            Token::LBracket => "'['",
+            Token::RBracket => "']'",
+            Token::Assignment => "'='",
+            Token::Semicolon => "';'",
+            _ => "another token",
+        };
+        let next = self.take_expecting(vec![description])?;
+        if next != expected {
+            Err(self.unexpected(next, vec![description]))
         } else {
-            Err(String::from("Unexpected end of input"))
+            Ok(next)
         }
     }
 }
@@ -1,3 +1,5 @@
+use super::error::ThbcError;
+use std::fmt;
 use std::iter::Peekable;
 use std::str::Chars;
 
@@ -12,61 +14,274 @@ use std::str::Chars;
  * to this code to anyone other than the course staff.
  */
 
+/**
+ * Where a token was found in the input: the byte offsets it spans and the
+ * line and column, both counted from 1, of its first character.
+ * The default span, on line 0, stands for no position at all.
+ */
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
 /**
  * The tokens types of `thbc` are defined below.
  */
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Token {
-    Unknown(char),
     Operator(char),
-    Number(f64),
-    Register(char),
+    Number(String),
+    Str(String),
+    Ident(String),
+    Keyword(Keyword),
+    RelOp(RelOp),
+    And,
+    Or,
+    Not,
     Assignment,
+    CompoundAssignment(char),
+    Increment,
+    Decrement,
     LParen,
     RParen,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Semicolon,
+    Comma,
+    Newline,
+}
+
+/**
+ * The relational operators `<`, `<=`, `>`, `>=`, `==` and `!=`.
+ */
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RelOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+/**
+ * The reserved words of GNU bc. They can never be used as identifiers.
+ */
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Keyword {
+    Auto,
+    Break,
+    Continue,
+    Define,
+    Else,
+    For,
+    Halt,
+    Ibase,
+    If,
+    Last,
+    Length,
+    Limits,
+    Obase,
+    Print,
+    Quit,
+    Read,
+    Return,
+    Scale,
+    Sqrt,
+    Warranty,
+    While,
+}
+
+impl Keyword {
+    fn from_word(word: &str) -> Option<Keyword> {
+        match word {
+            "auto" => Some(Keyword::Auto),
+            "break" => Some(Keyword::Break),
+            "continue" => Some(Keyword::Continue),
+            "define" => Some(Keyword::Define),
+            "else" => Some(Keyword::Else),
+            "for" => Some(Keyword::For),
+            "halt" => Some(Keyword::Halt),
+            "ibase" => Some(Keyword::Ibase),
+            "if" => Some(Keyword::If),
+            "last" => Some(Keyword::Last),
+            "length" => Some(Keyword::Length),
+            "limits" => Some(Keyword::Limits),
+            "obase" => Some(Keyword::Obase),
+            "print" => Some(Keyword::Print),
+            "quit" => Some(Keyword::Quit),
+            "read" => Some(Keyword::Read),
+            "return" => Some(Keyword::Return),
+            "scale" => Some(Keyword::Scale),
+            "sqrt" => Some(Keyword::Sqrt),
+            "warranty" => Some(Keyword::Warranty),
+            "while" => Some(Keyword::While),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * Tokens are shown the way they are spelled in the input, so that an error
+ * names what the user wrote: `'*'`, `identifier e3` or `keyword define`.
+ */
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Operator(op) => write!(f, "'{}'", op),
+            Token::Number(value) => write!(f, "number {}", value),
+            Token::Str(text) => write!(f, "string \"{}\"", text),
+            Token::Ident(name) => write!(f, "identifier {}", name),
+            Token::Keyword(keyword) => write!(f, "keyword {}", keyword.word()),
+            Token::RelOp(op) => write!(f, "'{}'", op.symbol()),
+            Token::And => write!(f, "'&&'"),
+            Token::Or => write!(f, "'||'"),
+            Token::Not => write!(f, "'!'"),
+            Token::Assignment => write!(f, "'='"),
+            Token::CompoundAssignment(op) => write!(f, "'{}='", op),
+            Token::Increment => write!(f, "'++'"),
+            Token::Decrement => write!(f, "'--'"),
+            Token::LParen => write!(f, "'('"),
+            Token::RParen => write!(f, "')'"),
+            Token::LBrace => write!(f, "'{{'"),
+            Token::RBrace => write!(f, "'}}'"),
+            Token::LBracket => write!(f, "'['"),
+            Token::RBracket => write!(f, "']'"),
+            Token::Semicolon => write!(f, "';'"),
+            Token::Comma => write!(f, "','"),
+            Token::Newline => write!(f, "newline"),
+        }
+    }
+}
+
+impl RelOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            RelOp::Lt => "<",
+            RelOp::Le => "<=",
+            RelOp::Gt => ">",
+            RelOp::Ge => ">=",
+            RelOp::Eq => "==",
+            RelOp::Ne => "!=",
+        }
+    }
+}
+
+impl Keyword {
+    fn word(self) -> &'static str {
+        match self {
+            Keyword::Auto => "auto",
+            Keyword::Break => "break",
+            Keyword::Continue => "continue",
+            Keyword::Define => "define",
+            Keyword::Else => "else",
+            Keyword::For => "for",
+            Keyword::Halt => "halt",
+            Keyword::Ibase => "ibase",
+            Keyword::If => "if",
+            Keyword::Last => "last",
+            Keyword::Length => "length",
+            Keyword::Limits => "limits",
+            Keyword::Obase => "obase",
+            Keyword::Print => "print",
+            Keyword::Quit => "quit",
+            Keyword::Read => "read",
+            Keyword::Return => "return",
+            Keyword::Scale => "scale",
+            Keyword::Sqrt => "sqrt",
+            Keyword::Warranty => "warranty",
+            Keyword::While => "while",
+        }
+    }
 }
 
 /**
  * The internal state of a Tokenizer is maintained by a peekable character
- * iterator over a &str's Chars.
+ * iterator over a &str's Chars, along with the position of the next
+ * character in the input. A clone picks up where the original is, which
+ * lets the parser look further ahead than the next token.
  */
+#[derive(Clone)]
 pub struct Tokenizer<'str> {
+    input: &'str str,
     chars: Peekable<Chars<'str>>,
+    offset: usize,
+    line: usize,
+    column: usize,
 }
 
 impl<'str> Tokenizer<'str> {
-    pub fn new(input: &'str str) -> Tokenizer {
+    pub fn new(input: &'str str) -> Tokenizer<'str> {
         Tokenizer {
+            input,
             chars: input.chars().peekable(),
+            offset: 0,
+            line: 1,
+            column: 1,
         }
     }
+
+    /**
+     * The whole input being tokenized, which spans are offsets into.
+     */
+    pub fn input(&self) -> &'str str {
+        self.input
+    }
 }
 
 /**
  * The Iterator trait is implemented for Tokenizer. It will produce items of
- * type Token and has a `next` method that returns Option<Token>.
+ * type Result<(Token, Span), ThbcError> and has a `next` method that returns
+ * Option<Result<(Token, Span), ThbcError>>.
  */
 impl<'str> Iterator for Tokenizer<'str> {
-    type Item = Token;
+    type Item = Result<(Token, Span), ThbcError>;
 
     /**
-     * The `next` method ignores leading whitespace and returns the next
-     * complete Some(Token) in the Tokenizer's input string or None at all.
+     * The `next` method ignores leading whitespace and comments and returns
+     * the next complete Token in the Tokenizer's input string along with its
+     * Span, or None at all. Input that isn't a token is an Err spanning the
+     * offending characters, and lexing carries on after them.
      */
-    fn next(&mut self) -> Option<Token> {
-        self.lex_whitespace();
-        if let Some(c) = self.chars.peek() {
-            Some(match c {
-                '+' | '-' | '*' | '/' | '^' => self.lex_operator(),
-                '=' => self.lex_assignment(),
-                '0'...'9' => self.lex_number(),
-                'a'...'z' => self.lex_register(),
-                '(' | ')' => self.lex_paren(),
-                _ => self.lex_unknown(),
-            })
-        } else {
-            None
+    fn next(&mut self) -> Option<Result<(Token, Span), ThbcError>> {
+        if let Err(error) = self.lex_trivia() {
+            return Some(Err(error));
         }
+        let c = *self.chars.peek()?;
+        let (start, line, column) = (self.offset, self.line, self.column);
+        let token = match c {
+            '+' | '-' | '*' | '/' | '%' | '^' => Ok(self.lex_operator()),
+            '=' | '<' | '>' | '!' => self.lex_relational(),
+            '&' | '|' => self.lex_logical(),
+            '0'..='9' | 'A'..='F' | '.' => self.lex_number(),
+            'a'..='z' => Ok(self.lex_word()),
+            '"' => self.lex_string(),
+            '(' => Ok(self.lex_single(Token::LParen)),
+            ')' => Ok(self.lex_single(Token::RParen)),
+            '{' => Ok(self.lex_single(Token::LBrace)),
+            '}' => Ok(self.lex_single(Token::RBrace)),
+            '[' => Ok(self.lex_single(Token::LBracket)),
+            ']' => Ok(self.lex_single(Token::RBracket)),
+            ';' => Ok(self.lex_single(Token::Semicolon)),
+            ',' => Ok(self.lex_single(Token::Comma)),
+            '\n' => Ok(self.lex_single(Token::Newline)),
+            _ => self.lex_unknown(),
+        };
+        let span = Span {
+            start,
+            end: self.offset,
+            line,
+            column,
+        };
+        Some(match token {
+            Ok(token) => Ok((token, span)),
+            Err(message) => Err(ThbcError::Lex { message, span }),
+        })
     }
 }
 
@@ -75,72 +290,600 @@ impl<'str> Iterator for Tokenizer<'str> {
  * so these are internal methods only.
  */
 impl<'str> Tokenizer<'str> {
+    /**
+     * Takes the next character, keeping track of where in the input the
+     * character after it is.
+     */
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
     fn lex_whitespace(&mut self) {
         while let Some(c) = self.chars.peek() {
             match c {
-                ' ' | '\t' | '\n' => self.chars.next(),
+                ' ' | '\t' => self.bump(),
                 _ => break,
             };
         }
     }
 
-    fn lex_register(&mut self) -> Token {
-        let c = self.chars.next().unwrap();
-        match c {
-            'a'...'z' => Token::Register(c),
-            _ => panic!("unknown register"),
+    /**
+     * Skips whitespace and comments, which separate tokens without being
+     * tokens themselves. A `#` comment runs up to the end of its line, whose
+     * newline still ends a statement, while a `/* */` comment may span lines.
+     */
+    fn lex_trivia(&mut self) -> Result<(), ThbcError> {
+        loop {
+            self.lex_whitespace();
+            match self.chars.peek().copied() {
+                Some('#') => self.lex_line_comment(),
+                Some('/') if self.chars.clone().nth(1) == Some('*') => self.lex_block_comment()?,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn lex_line_comment(&mut self) {
+        while let Some(c) = self.chars.peek() {
+            match c {
+                '\n' => break,
+                _ => self.bump(),
+            };
+        }
+    }
+
+    /**
+     * A block comment that is never closed swallows the rest of the input,
+     * so the error spans all of it.
+     */
+    fn lex_block_comment(&mut self) -> Result<(), ThbcError> {
+        let (start, line, column) = (self.offset, self.line, self.column);
+        self.bump(); //takes in the /
+        self.bump(); //takes in the *
+        loop {
+            match self.bump() {
+                Some('*') if self.chars.peek() == Some(&'/') => {
+                    self.bump();
+                    return Ok(());
+                }
+                Some(_) => {}
+                None => {
+                    let span = Span { start, end: self.offset, line, column };
+                    return Err(ThbcError::Lex { message: String::from("Unterminated comment"), span });
+                }
+            }
         }
     }
 
-    fn lex_assignment(&mut self) -> Token {
-        let c = self.chars.next().unwrap();
-        match c {
-            '=' => Token::Assignment,
-            _ => panic!("Unexpected assignment helper"),
+    /**
+     * Words are a lowercase letter followed by any lowercase letters, digits
+     * and underscores. A word is a keyword if it is reserved and otherwise an
+     * identifier.
+     */
+    fn lex_word(&mut self) -> Token {
+        let mut word = String::new();
+        while let Some(c) = self.chars.peek() {
+            match c {
+                'a'..='z' | '0'..='9' | '_' => word.push(self.bump().unwrap()),
+                _ => break,
+            }
+        }
+        match Keyword::from_word(&word) {
+            Some(keyword) => Token::Keyword(keyword),
+            None => Token::Ident(word),
         }
     }
 
-    fn lex_paren(&mut self) -> Token {
-        let c = self.chars.next().unwrap();
-        match c {
-            '(' => Token::LParen,
-            ')' => Token::RParen,
-            _ => panic!("unknown register"),
+    /**
+     * Strings run up to the next `"`, newlines included, and hold their text
+     * just as it was written. Only `print` replaces the escapes in them, so
+     * they are left to `unescape`.
+     */
+    fn lex_string(&mut self) -> Result<Token, String> {
+        self.bump(); //takes in the opening "
+        let mut text = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(Token::Str(text)),
+                Some(c) => text.push(c),
+                None => return Err(String::from("Unterminated string")),
+            }
         }
     }
 
-    fn lex_unknown(&mut self) -> Token {
-        let c = self.chars.next().unwrap();
-        Token::Unknown(c)
+    /**
+     * Tokens of a single character have already been recognized by `next`,
+     * which hands over the token to produce once the character is taken.
+     */
+    fn lex_single(&mut self, token: Token) -> Token {
+        self.bump();
+        token
     }
 
+    /**
+     * An arithmetic operator followed by `=` assigns, as in `x += 2`, and
+     * `++` and `--` are taken whole, so `a--b` is not `a - -b`.
+     */
     fn lex_operator(&mut self) -> Token {
-        let c = self.chars.next().unwrap();
-        match c {
-            '=' | '+' | '-' | '*' | '/' | '^' => Token::Operator(c),
-            _ => panic!("unknown operator"),
+        let c = self.bump().unwrap();
+        match (c, self.chars.peek()) {
+            (_, Some('=')) => self.lex_single(Token::CompoundAssignment(c)),
+            ('+', Some('+')) => self.lex_single(Token::Increment),
+            ('-', Some('-')) => self.lex_single(Token::Decrement),
+            _ => Token::Operator(c),
+        }
+    }
+
+    /**
+     * `=`, `<`, `>` and `!` may each be followed by a `=`, so one more
+     * character is needed to tell `=` from `==` and `!` from `!=`.
+     */
+    fn lex_relational(&mut self) -> Result<Token, String> {
+        let c = self.bump().unwrap();
+        let equals = if let Some('=') = self.chars.peek() {
+            self.bump();
+            true
+        } else {
+            false
+        };
+        match (c, equals) {
+            ('=', false) => Ok(Token::Assignment),
+            ('=', true) => Ok(Token::RelOp(RelOp::Eq)),
+            ('<', false) => Ok(Token::RelOp(RelOp::Lt)),
+            ('<', true) => Ok(Token::RelOp(RelOp::Le)),
+            ('>', false) => Ok(Token::RelOp(RelOp::Gt)),
+            ('>', true) => Ok(Token::RelOp(RelOp::Ge)),
+            ('!', false) => Ok(Token::Not),
+            ('!', true) => Ok(Token::RelOp(RelOp::Ne)),
+            _ => unreachable!("not a relational character: {:?}", c),
+        }
+    }
+
+    /**
+     * `&&` and `||` are the only tokens made of `&` and `|`, which are not
+     * operators on their own.
+     */
+    fn lex_logical(&mut self) -> Result<Token, String> {
+        let c = self.bump().unwrap();
+        if self.chars.peek() != Some(&c) {
+            return Err(format!("Unexpected character {:?}", c));
         }
+        self.bump();
+        Ok(if c == '&' { Token::And } else { Token::Or })
     }
 
-    fn lex_number(&mut self) -> Token {
+    fn lex_unknown(&mut self) -> Result<Token, String> {
+        let c = self.bump().unwrap();
+        Err(format!("Unexpected character {:?}", c))
+    }
+
+    /**
+     * Like bc, either side of the point may be left out, as in `.5` or `1.`,
+     * but not both. A second point makes the whole run of digits and points
+     * a malformed number.
+     */
+    fn lex_number(&mut self) -> Result<Token, String> {
         let mut s = String::new();
         self.lex_digits(&mut s);
-        if let Some(c) = self.chars.peek() {
-            if *c == '.' {
-                self.chars.next();
-                s.push('.');
-                self.lex_digits(&mut s);
+        if let Some('.') = self.chars.peek() {
+            self.bump();
+            s.push('.');
+            self.lex_digits(&mut s);
+        }
+        if let Some('.') = self.chars.peek() {
+            while let Some(c) = self.chars.peek() {
+                match c {
+                    '0'..='9' | 'A'..='F' | '.' => s.push(self.bump().unwrap()),
+                    _ => break,
+                }
             }
+            return Err(format!("Malformed number {}", s));
+        }
+        if s == "." {
+            return Err(String::from("Malformed number ."));
         }
-        Token::Number(s.parse::<f64>().unwrap())
+        Ok(Token::Number(s))
     }
 
+    /**
+     * Like bc, the uppercase letters A-F are digits so that numbers can be
+     * entered in bases up to 16. Their value is decided when evaluating,
+     * based on `ibase`.
+     */
     fn lex_digits(&mut self, buffer: &mut String) {
         while let Some(c) = self.chars.peek() {
             match c {
-                '0'...'9' => buffer.push(self.chars.next().unwrap()),
+                '0'..='9' | 'A'..='F' => buffer.push(self.bump().unwrap()),
                 _ => break,
             }
         }
     }
 }
+
+/**
+ * Replaces the escapes in the text of a string that is printed with `print`.
+ * `\q` stands for a `"`, since nothing can keep a `"` from ending a string.
+ */
+pub fn unescape(text: &str) -> Result<String, String> {
+    let mut chars = text.chars();
+    let mut unescaped = String::new();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('a') => unescaped.push('\u{7}'),
+            Some('b') => unescaped.push('\u{8}'),
+            Some('f') => unescaped.push('\u{c}'),
+            Some('n') => unescaped.push('\n'),
+            Some('q') => unescaped.push('"'),
+            Some('r') => unescaped.push('\r'),
+            Some('t') => unescaped.push('\t'),
+            Some('\\') => unescaped.push('\\'),
+            Some(c) => return Err(format!("Unknown escape \\{} in string", c)),
+            None => return Err(String::from("Unfinished escape in string")),
+        }
+    }
+    Ok(unescaped)
+}
+
+#[cfg(test)]
+mod lex {
+    use super::*;
+
+    fn tokens(input: &str) -> Vec<Token> {
+        Tokenizer::new(input).map(|item| item.unwrap().0).collect()
+    }
+
+    fn spans(input: &str) -> Vec<(usize, usize, usize, usize)> {
+        Tokenizer::new(input)
+            .map(|item| item.unwrap().1)
+            .map(|span| (span.start, span.end, span.line, span.column))
+            .collect()
+    }
+
+    #[test]
+    fn lex_hex_digits() {
+        assert_eq!(
+            vec![
+                Token::Number(String::from("FF")),
+                Token::Operator('+'),
+                Token::Number(String::from("1A.C")),
+            ],
+            tokens("FF + 1A.C")
+        );
+    }
+
+    #[test]
+    fn lex_special_variables() {
+        assert_eq!(
+            vec![
+                Token::Keyword(Keyword::Obase),
+                Token::Assignment,
+                Token::Keyword(Keyword::Ibase),
+                Token::Operator('*'),
+                Token::Keyword(Keyword::Scale),
+            ],
+            tokens("obase=ibase*scale")
+        );
+    }
+
+    #[test]
+    fn lex_identifiers() {
+        assert_eq!(
+            vec![
+                Token::Ident(String::from("sc")),
+                Token::Operator('-'),
+                Token::Ident(String::from("total_2")),
+                Token::Operator('*'),
+                Token::Ident(String::from("x")),
+            ],
+            tokens("sc-total_2*x")
+        );
+    }
+
+    #[test]
+    fn lex_keywords() {
+        assert_eq!(
+            vec![
+                Token::Keyword(Keyword::If),
+                Token::Keyword(Keyword::Define),
+                Token::Ident(String::from("iff")),
+                Token::Keyword(Keyword::Quit),
+            ],
+            tokens("if define iff quit")
+        );
+    }
+
+    #[test]
+    fn lex_spans() {
+        assert_eq!(
+            vec![(0, 3, 1, 1), (4, 5, 1, 5), (6, 11, 1, 7)],
+            spans("abc + 12.50")
+        );
+    }
+
+    #[test]
+    fn lex_spans_across_lines() {
+        assert_eq!(
+            vec![(0, 1, 1, 1), (1, 2, 1, 2), (2, 3, 2, 1), (3, 4, 3, 1), (4, 5, 3, 2), (7, 9, 4, 3), (9, 10, 4, 5)],
+            spans("1\n\n+\n\t\tFF\n")
+        );
+    }
+
+    #[test]
+    fn lex_items_carry_spans() {
+        let span = |start, end, line, column| Span { start, end, line, column };
+        assert_eq!(
+            vec![
+                Ok((Token::Ident(String::from("x")), span(0, 1, 1, 1))),
+                Ok((Token::CompoundAssignment('+'), span(2, 4, 1, 3))),
+                Err(ThbcError::Lex { message: String::from("Unexpected character '@'"), span: span(5, 6, 1, 6) }),
+                Ok((Token::Newline, span(6, 7, 1, 7))),
+                Ok((Token::Str(String::from("a\nb")), span(7, 12, 2, 1))),
+            ],
+            Tokenizer::new("x += @\n\"a\nb\"").collect::<Vec<_>>()
+        );
+        assert_ne!(span(0, 1, 1, 1), span(0, 1, 1, 2));
+    }
+
+    #[test]
+    fn lex_statement_separators() {
+        assert_eq!(
+            vec![
+                Token::LBrace,
+                Token::Number(String::from("1")),
+                Token::Semicolon,
+                Token::Newline,
+                Token::Ident(String::from("x")),
+                Token::RBrace,
+                Token::Newline,
+            ],
+            tokens("{1;\n  x}\n")
+        );
+    }
+
+    #[test]
+    fn lex_comma() {
+        assert_eq!(
+            vec![
+                Token::Ident(String::from("f")),
+                Token::LParen,
+                Token::Ident(String::from("x")),
+                Token::Comma,
+                Token::Number(String::from("2")),
+                Token::RParen,
+            ],
+            tokens("f(x,2)")
+        );
+    }
+
+    #[test]
+    fn lex_brackets() {
+        assert_eq!(
+            vec![
+                Token::Ident(String::from("a")),
+                Token::LBracket,
+                Token::Number(String::from("1")),
+                Token::RBracket,
+                Token::Ident(String::from("b")),
+                Token::LBracket,
+                Token::RBracket,
+            ],
+            tokens("a[1] b[]")
+        );
+    }
+
+    #[test]
+    fn lex_modulo() {
+        assert_eq!(
+            vec![
+                Token::Number(String::from("7")),
+                Token::Operator('%'),
+                Token::Number(String::from("2")),
+            ],
+            tokens("7%2")
+        );
+    }
+
+    #[test]
+    fn lex_compound_assignments() {
+        let ops = ['+', '-', '*', '/', '%', '^'];
+        assert_eq!(
+            ops.iter().map(|op| Token::CompoundAssignment(*op)).collect::<Vec<_>>(),
+            tokens("+= -= *= /= %= ^=")
+        );
+        assert_eq!(
+            vec![Token::Ident(String::from("x")), Token::Operator('+'), Token::Assignment, Token::Number(String::from("1"))],
+            tokens("x+ =1")
+        );
+    }
+
+    #[test]
+    fn lex_increment_decrement() {
+        assert_eq!(
+            vec![
+                Token::Increment,
+                Token::Ident(String::from("a")),
+                Token::Decrement,
+                Token::Ident(String::from("b")),
+                Token::Operator('-'),
+                Token::Operator('-'),
+                Token::Number(String::from("1")),
+            ],
+            tokens("++a--b- -1")
+        );
+    }
+
+    #[test]
+    fn lex_relational_operators() {
+        assert_eq!(
+            vec![
+                Token::RelOp(RelOp::Lt),
+                Token::RelOp(RelOp::Le),
+                Token::RelOp(RelOp::Gt),
+                Token::RelOp(RelOp::Ge),
+                Token::RelOp(RelOp::Eq),
+                Token::RelOp(RelOp::Ne),
+                Token::Assignment,
+            ],
+            tokens("< <= > >= == != =")
+        );
+    }
+
+    #[test]
+    fn lex_relational_without_spaces() {
+        assert_eq!(
+            vec![
+                Token::Ident(String::from("a")),
+                Token::RelOp(RelOp::Le),
+                Token::Number(String::from("1")),
+                Token::RelOp(RelOp::Eq),
+                Token::Assignment,
+                Token::Number(String::from("2")),
+            ],
+            tokens("a<=1===2")
+        );
+    }
+
+    fn errors(input: &str) -> Vec<(String, usize, usize)> {
+        Tokenizer::new(input)
+            .filter_map(|item| match item {
+                Err(ThbcError::Lex { message, span }) => Some((message, span.start, span.end)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lex_optional_sides_of_point() {
+        assert_eq!(
+            vec![
+                Token::Number(String::from(".5")),
+                Token::Operator('+'),
+                Token::Number(String::from("1.")),
+            ],
+            tokens(".5+1.")
+        );
+    }
+
+    #[test]
+    fn lex_err_malformed_number() {
+        assert_eq!(vec![(String::from("Malformed number 1.2.3"), 0, 5)], errors("1.2.3 + 1"));
+        assert_eq!(vec![(String::from("Malformed number ."), 2, 3)], errors("1 . 2"));
+    }
+
+    #[test]
+    fn lex_err_unknown_character() {
+        assert_eq!(vec![(String::from("Unexpected character '@'"), 2, 3)], errors("1 @ 2"));
+        assert_eq!(vec![(String::from("Unexpected character 'é'"), 0, 2)], errors("é+x"));
+        assert_eq!(vec![(String::from("Unexpected character 'G'"), 0, 1)], errors("G"));
+    }
+
+    #[test]
+    fn lex_logical_operators() {
+        assert_eq!(
+            vec![
+                Token::Not,
+                Token::Ident(String::from("a")),
+                Token::And,
+                Token::Number(String::from("1")),
+                Token::Or,
+                Token::Not,
+                Token::RelOp(RelOp::Ne),
+            ],
+            tokens("!a&&1||! !=")
+        );
+    }
+
+    #[test]
+    fn lex_err_single_ampersand_or_bar() {
+        assert_eq!(vec![(String::from("Unexpected character '&'"), 2, 3)], errors("1 & 2"));
+        assert_eq!(vec![(String::from("Unexpected character '|'"), 1, 2)], errors("1|"));
+    }
+
+    #[test]
+    fn display_spelling() {
+        let shown: Vec<String> = tokens("e3 * 12 define <= += \"hi\" {\n").iter().map(Token::to_string).collect();
+        assert_eq!(
+            vec!["identifier e3", "'*'", "number 12", "keyword define", "'<='", "'+='", "string \"hi\"", "'{'", "newline"],
+            shown
+        );
+    }
+
+    #[test]
+    fn lex_strings() {
+        assert_eq!(
+            vec![
+                Token::Keyword(Keyword::Print),
+                Token::Str(String::from("total: ")),
+                Token::Comma,
+                Token::Ident(String::from("t")),
+                Token::Comma,
+                Token::Str(String::from("\\n")),
+            ],
+            tokens("print \"total: \", t, \"\\n\"")
+        );
+        assert_eq!(
+            vec![Token::Str(String::from("\\qa\\tb\\\\c\n[d]\\n")), Token::Newline],
+            tokens("\"\\qa\\tb\\\\c\n[d]\\n\"\n")
+        );
+        assert_eq!(vec![Token::Str(String::from("ab\\")), Token::Str(String::from("\\x"))], tokens("\"ab\\\" \"\\x\""));
+    }
+
+    #[test]
+    fn lex_err_strings() {
+        assert_eq!(vec![(String::from("Unterminated string"), 2, 7)], errors("1 \"abc\n"));
+    }
+
+    #[test]
+    fn unescape_strings() {
+        assert_eq!(Ok(String::from("\"a\tb\\c\n[d]\n")), unescape("\\qa\\tb\\\\c\n[d]\\n"));
+        assert_eq!(Ok(String::from("\u{7}\u{8}\u{c}\r")), unescape("\\a\\b\\f\\r"));
+        assert_eq!(Err(String::from("Unknown escape \\x in string")), unescape("\\x\\y z"));
+        assert_eq!(Err(String::from("Unfinished escape in string")), unescape("ab\\"));
+    }
+
+    #[test]
+    fn lex_comments() {
+        assert_eq!(
+            vec![
+                Token::Ident(String::from("a")),
+                Token::Operator('/'),
+                Token::Ident(String::from("b")),
+                Token::Newline,
+                Token::Number(String::from("1")),
+                Token::Operator('/'),
+                Token::Operator('*'),
+                Token::Number(String::from("2")),
+            ],
+            tokens("a /* over\nlines **/ /**/ /b # to the end */\n1/ *2#")
+        );
+        assert_eq!(vec![Token::Str(String::from("# /* */"))], tokens("\"# /* */\""));
+    }
+
+    #[test]
+    fn lex_err_unterminated_comment() {
+        assert_eq!(vec![(String::from("Unterminated comment"), 2, 12)], errors("1 /* 2 *\n/ 3"));
+        let items: Vec<_> = Tokenizer::new("x /* y").collect();
+        assert_eq!(2, items.len());
+        assert!(items[1].is_err());
+    }
+
+    #[test]
+    fn lex_continues_after_error() {
+        let items: Vec<_> = Tokenizer::new("1 $ 2").collect();
+        assert_eq!(3, items.len());
+        assert!(items[1].is_err());
+        assert_eq!(Token::Number(String::from("2")), items[2].as_ref().unwrap().0);
+    }
+}
@@ -0,0 +1,247 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+
+/**
+ * These tests stand a script that echoes its input in for dc, so the
+ * output is exactly the dc program thbc piped to its backend.
+ */
+fn thbc_with_echo() -> Command {
+    let mut cmd = Command::main_binary().unwrap();
+    cmd.args(["--backend", "dc", "--dc-path", ECHO_DC]);
+    cmd
+}
+
+const ECHO_DC: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fake_dc/echo");
+const NOISY_DC: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fake_dc/noisy");
+
+#[test]
+fn pipes_each_line_to_dc() {
+    thbc_with_echo()
+        .with_stdin()
+        .buffer("x = 3*4\nx + 1\nquit\n")
+        .assert()
+        .success()
+        .stdout("3 4 * sx\nlx 1 + p\n");
+}
+
+#[test]
+fn reports_parse_errors_without_piping() {
+    thbc_with_echo()
+        .with_stdin()
+        .buffer("1 +\n2\nquit\n")
+        .assert()
+        .failure()
+        .stdout("2 p\n")
+        .stderr("thbc: 1:4: error[E0002]: Unexpected newline, expected an expression\n1 +\n   ^\n");
+}
+
+#[test]
+fn forwards_dc_errors() {
+    Command::main_binary()
+        .unwrap()
+        .args(["--backend", "dc", "--dc-path", NOISY_DC])
+        .with_stdin()
+        .buffer("1\nquit\n")
+        .assert()
+        .failure()
+        .stdout("out: 1 p\n")
+        .stderr("thbc: err: 1 p\n");
+}
+
+#[test]
+fn waits_for_dc_output_before_the_next_line() {
+    // dc's own errors may trail, so only thbc's are kept in the merged output.
+    let script = "\"$0\" --backend dc --dc-path \"$1\" 2>&1 | grep -v '^thbc: err: '";
+    Command::new("sh")
+        .args(["-c", script, env!("CARGO_BIN_EXE_thbc"), NOISY_DC])
+        .with_stdin()
+        .buffer("1\n2 +\n3\nquit\n")
+        .assert()
+        .stdout("out: 1 p\nthbc: 1:4: error[E0002]: Unexpected newline, expected an expression\n2 +\n   ^\nout: 3 p\n");
+}
+
+#[test]
+fn fails_when_dc_cannot_start() {
+    Command::main_binary()
+        .unwrap()
+        .args(["--backend", "dc", "--dc-path", "thbc-no-such-dc"])
+        .with_stdin()
+        .buffer("quit\n")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn native_backend_needs_no_dc() {
+    Command::main_binary()
+        .unwrap()
+        .args(["--dc-path", "thbc-no-such-dc"])
+        .with_stdin()
+        .buffer("x = 7\nx / 2\n-x * 2\nquit\n")
+        .assert()
+        .success()
+        .stdout("3\n-14\n");
+}
+
+#[test]
+fn runs_every_statement_on_a_line() {
+    Command::main_binary()
+        .unwrap()
+        .with_stdin()
+        .buffer("x = 2; x; { x * 3; 1 }\n\n;\nx\nquit\n")
+        .assert()
+        .success()
+        .stdout("2\n6\n1\n2\n");
+    thbc_with_echo()
+        .with_stdin()
+        .buffer("x = 2; x; { x * 3; 1 }\n\nquit\n")
+        .assert()
+        .success()
+        .stdout("2 sx lx p lx 3 * p 1 p\n");
+}
+
+#[test]
+fn keeps_going_after_bad_input() {
+    Command::main_binary()
+        .unwrap()
+        .with_stdin()
+        .buffer("1.2.3\n\u{e9} + 1\n2\nquit\n")
+        .assert()
+        .failure()
+        .stdout("2\n")
+        .stderr(
+            "thbc: 1:1: error[E0001]: Malformed number 1.2.3\n1.2.3\n^\n\
+             thbc: 1:1: error[E0001]: Unexpected character '\u{e9}'\n\u{e9} + 1\n^\n",
+        );
+}
+
+#[test]
+fn skips_comments() {
+    Command::main_binary()
+        .unwrap()
+        .with_stdin()
+        .buffer("x = 2 # two\n/* the */ x # /* no block here\n/* spans\nlines */ 3\nx /* open\nquit\n")
+        .assert()
+        .failure()
+        .stdout("2\n3\n")
+        .stderr("thbc: 1:3: error[E0001]: Unterminated comment\nx /* open\n  ^\n");
+}
+
+#[test]
+fn prints_escapes_only_in_print() {
+    Command::main_binary()
+        .unwrap()
+        .with_stdin()
+        .buffer("\"a\\nb\"\nprint \"|a\\nb\\n\"\n")
+        .assert()
+        .success()
+        .stdout("a\\nb|a\nb\n");
+}
+
+/**
+ * thbc run from the directory of the test scripts, so errors name them
+ * just like they are given.
+ */
+fn thbc_in_scripts() -> Command {
+    let mut cmd = Command::main_binary().unwrap();
+    cmd.current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/scripts"));
+    cmd
+}
+
+#[test]
+fn runs_files_then_stdin() {
+    thbc_in_scripts()
+        .args(["lib.bc", "errors.bc"])
+        .with_stdin()
+        .buffer("fact(sq(2))\nquit\n")
+        .assert()
+        .failure()
+        .stdout("sq: 16\n0\n1\n24\n")
+        .stderr(
+            "thbc: errors.bc:2:8: error[E0002]: Unexpected newline, expected an expression\nx = 1 +\n       ^\n\
+             thbc: errors.bc:3:9: error[E0006]: Divide by zero\nfact(5) / 0\n        ^\n\
+             thbc: errors.bc:7:5: error[E0003]: Unexpected end of input, expected '}'\n{ 1\n   ^\n",
+        );
+}
+
+#[test]
+fn quiet_skips_stdin() {
+    thbc_in_scripts()
+        .args(["--quiet", "lib.bc"])
+        .with_stdin()
+        .buffer("sq(3)\nquit\n")
+        .assert()
+        .success()
+        .stdout("");
+}
+
+#[test]
+fn fails_on_missing_file() {
+    thbc_in_scripts()
+        .args(["lib.bc", "thbc-no-such-file.bc"])
+        .with_stdin()
+        .buffer("quit\n")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn ends_at_end_of_input() {
+    Command::main_binary()
+        .unwrap()
+        .with_stdin()
+        .buffer("x = 1 + 2\nx")
+        .assert()
+        .success()
+        .stdout("3\n");
+    thbc_in_scripts().arg("lib.bc").with_stdin().buffer("").assert().success().stdout("");
+}
+
+#[test]
+fn quits_wherever_quit_is_read() {
+    Command::main_binary()
+        .unwrap()
+        .with_stdin()
+        .buffer("x = 5; x\nprint \"quit\\n\" # quit\nx; if (0) quit\nx\n")
+        .assert()
+        .success()
+        .stdout("5\nquit\n");
+}
+
+#[test]
+fn quits_before_failing_statements_run() {
+    Command::main_binary()
+        .unwrap()
+        .with_stdin()
+        .buffer("1\n1/0; quit\n")
+        .assert()
+        .success()
+        .stdout("1\n");
+}
+
+#[test]
+fn stops_runaway_loops() {
+    Command::main_binary()
+        .unwrap()
+        .args(["--max-iterations", "2"])
+        .with_stdin()
+        .buffer("while (1) 7\n3\nquit\n")
+        .assert()
+        .failure()
+        .stdout("7\n7\n3\n")
+        .stderr("thbc: 1:1: error[E0006]: Loop ran for more than 2 iterations\nwhile (1) 7\n^\n");
+}
+
+#[test]
+fn stops_runaway_recursion() {
+    Command::main_binary()
+        .unwrap()
+        .with_stdin()
+        .buffer("define f(x) { return f(x) + 1 }\nf(1)\nx\nquit\n")
+        .assert()
+        .failure()
+        .stdout("0\n")
+        .stderr("thbc: 1:1: error[E0006]: Function calls nested too deeply\nf(1)\n^\n");
+}
@@ -0,0 +1,169 @@
+extern crate assert_cmd;
+
+use assert_cmd::prelude::*;
+use std::process::{Command, Stdio};
+
+/**
+ * Runs the same input through the native and the dc backends and checks
+ * that they agree. The dc executable is taken from `THBC_DC` (or `dc` on
+ * PATH). These tests are ignored by default, so run them where dc is
+ * installed with `cargo test --test differential -- --ignored`; they fail
+ * when no dc can be started rather than passing without comparing.
+ */
+fn dc_path() -> String {
+    let path = std::env::var("THBC_DC").unwrap_or_else(|_| String::from("dc"));
+    let available = Command::new(&path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok();
+    assert!(available, "no dc at {:?}, set THBC_DC to a dc executable", path);
+    path
+}
+
+fn run(args: &[&str], input: &str) -> (String, String) {
+    let output = Command::main_binary()
+        .unwrap()
+        .args(args)
+        .with_stdin()
+        .buffer(format!("{}quit\n", input))
+        .output()
+        .unwrap();
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+fn assert_backends_agree(input: &str) {
+    let dc = dc_path();
+    let (native, _) = run(&["--backend", "native"], input);
+    let (piped, _) = run(&["--backend", "dc", "--dc-path", &dc], input);
+    assert_eq!(native, piped, "backends disagree on:\n{}", input);
+}
+
+#[test]
+#[ignore = "needs dc, run with --ignored"]
+fn arithmetic() {
+    assert_backends_agree("1+2*3\n2^3^2\n(1-4)/2\n7/2\n-7/2\n-2^2\n2^-1\n");
+}
+
+#[test]
+#[ignore = "needs dc, run with --ignored"]
+fn registers() {
+    assert_backends_agree("x = 3*4\nx + 1\ny\n(a = 2) * a\nb = c = 5\nb + c\n");
+}
+
+#[test]
+#[ignore = "needs dc, run with --ignored"]
+fn exact_decimals() {
+    assert_backends_agree("0.1+0.2\n12345678901234567890*98765432109876543210\n1.5*1.5\n2.50-2.5\n2^300\n.5+1.\n");
+}
+
+#[test]
+#[ignore = "needs dc, run with --ignored"]
+fn scale() {
+    assert_backends_agree(
+        "scale\n1/3\nscale = 5\n1/3\n-7/3\nscale\n2^-3\n1.1^10\n1.25*1.25\nscale(1.250)\nscale(7/2)\n(scale = 2) + 1\n1/8\n",
+    );
+}
+
+#[test]
+#[ignore = "needs dc, run with --ignored"]
+fn bases() {
+    assert_backends_agree(
+        "obase = 16\n255\n-255\n1/2\nscale = 3\n1/3\nobase = 2\n10\n0.5\nobase = 20\n45\n12345.6\nobase = A\nibase = 16\nFF\nA.8\nibase = 2\n101\nibase = 1010\nibase\nobase\n",
+    );
+}
+
+#[test]
+#[ignore = "needs dc, run with --ignored"]
+fn long_names() {
+    assert_backends_agree("total = 3\ncount_2 = total * 4\ntotal + count_2\nx = total\nx\nunset\n");
+    let names: Vec<String> = (0..100).map(|i| format!("v{} = {}", i, i)).collect();
+    assert_backends_agree(&format!("{}\nv0 + v67 + v68 + v99\ndefine sum(v50) {{ auto v100; v100 = v50 + v99; return (v100) }}; sum(1)\n", names.join("; ")));
+}
+
+#[test]
+#[ignore = "needs dc, run with --ignored"]
+fn relations() {
+    assert_backends_agree(
+        "1 < 2\n2 < 1\n1 <= 1\n2 >= 3\n3 > -3\n1.50 == 1.5\n1 != 1\nx = 5 < 7\nx\n(1 < 2) + (2 < 3)\n1 < 2 < 3\nlong = 4\nlong > 3 == 1\n",
+    );
+}
+
+#[test]
+#[ignore = "needs dc, run with --ignored"]
+fn logic() {
+    assert_backends_agree(
+        "1 && 2\n0 && 2\n0 || -1\n0 || 0\n!0\n!5\n!1 < 2\n1 + !0 + 1\n0 && (a = 5)\na\n1 || (b = 5)\nb\n0 || (c = 5)\nc\n1 && (1 && (0 || (d = 2)))\nd\n0 && 1 / 0\n",
+    );
+}
+
+#[test]
+#[ignore = "needs dc, run with --ignored"]
+fn modulo() {
+    assert_backends_agree("7 % 2\n-7 % 2\n7 % -2\n7.5 % 2\nscale = 2\n7.5 % 2\n1 % 3\n1 % 0.3\n1 + 10 % 4 * 3\n");
+}
+
+#[test]
+#[ignore = "needs dc, run with --ignored"]
+fn statements() {
+    assert_backends_agree("x = 2; x; { x * 3; y = x }\ny; {}; {{ 1 }; 2}\n\n;;\nscale = 3; 1/3; { scale = 1 }; 1/3\n");
+}
+
+#[test]
+#[ignore = "needs dc, run with --ignored"]
+fn conditionals() {
+    assert_backends_agree("x = 3; if (x > 2) x; if (x < 2) 1\nif (x == 3) { 4; 5 } else 6; if (0) 7 else { 8 }\n");
+    assert_backends_agree("if (x) if (x - 3) 1 else if (y = 5) y; y\nfor_x = 1; if (for_x && !y) 1 else { if (1) { 2 } else 3 }\n");
+}
+
+#[test]
+#[ignore = "needs dc, run with --ignored"]
+fn loops() {
+    assert_backends_agree("i = 0; while (i < 5) { i = i + 1; if (i == 2) continue; if (i == 4) break; i }; i\n");
+    assert_backends_agree("for (i = 0; i < 3; i = i + 1) for (j = 0; j < 3; j = j + 1) { if (j > i) break; i * 10 + j }\n");
+    assert_backends_agree("s = 0; for (;;) { s = s + 1; if (s > 3) { if (1) break } }; s; while (0) 1\n");
+    let deep = "if (1) ".repeat(12);
+    assert_backends_agree(&format!("ibase = 16; for (j = 0; j < 2; j++) {{ for (i = 0; i < 5; i++) {{ {}if (i == 2) break; i }}; j }}\n", deep));
+}
+
+#[test]
+#[ignore = "needs dc, run with --ignored"]
+fn functions() {
+    assert_backends_agree("define f(x) { auto y; y = x*x; return (y+1) }; f(3); y = 7; f(2) + y; y\n");
+    assert_backends_agree("define fact(n) { if (n < 2) return 1; return n * fact(n - 1) }; fact(12); n\n");
+    assert_backends_agree("define g(n) { auto i; for (i = 0; 1; i = i + 1) { if (i == n) return i * 100; i } }; g(3) + g(1); i\n");
+    assert_backends_agree("define h() { 4 }; h(); define s(a, b) { while (1) while (1) return a - b }; s(1, 2) * s(4, 1)\n");
+    assert_backends_agree("define fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2) }; fib(12)\n");
+}
+
+#[test]
+#[ignore = "needs dc, run with --ignored"]
+fn updates() {
+    assert_backends_agree("x = 5; x += 2; x; x++; x; ++x; x--; --x; x; y = x++ * 10; y; x\n");
+    assert_backends_agree("z = 7; z %= 4; z; z ^= 3; z; z -= 10; z /= 2; z; scale += 3; scale; 1/3; z *= -1.5\n");
+    assert_backends_agree("a[1] = 3; a[1] *= 4; a[1]; i = 1; a[i++] += 1; a[1]; i; a[i]++; a[2]; ++a[2]; -a[2]--\n");
+    assert_backends_agree("for (j = 0; j < 3; j++) j; define f(n) { auto s; while (n--) s += n; return (s) }; f(5); n\n");
+}
+
+#[test]
+#[ignore = "needs dc, run with --ignored"]
+fn strings() {
+    assert_backends_agree("print \"total: \", 3 * 4, \"\\n\"; t = 5; print \"[\", t, \"]\\n\", t + 1\n");
+    assert_backends_agree("ibase = 16; print \"[x]\\n\", A, \"\\n\"\n");
+    assert_backends_agree("print \"caf\u{e9} \u{20ac}\\n\"; \"\u{fc}\"\n");
+    assert_backends_agree("\"a[b]c\\td\\n\"; \"\\q\"; x = 1.5; print x, \"\\n\"; obase = 16; print 255, \"\\n\"\n");
+    assert_backends_agree("define f(n) { print \"n=\", n, \"\\n\"; return (n * 2) }; f(3); for (i = 0; i < 3; i++) print i, \" \"\n");
+}
+
+#[test]
+#[ignore = "needs dc, run with --ignored"]
+fn arrays() {
+    assert_backends_agree("a[3] = 5; a[3]; a[1] + a[3]; (a[2] = 7) * 2; a[2]; a = 4; a[3] + a\n");
+    assert_backends_agree("for (i = 0; i < 10; i = i + 1) s[i] = i * i; t = 0; for (i = 0; i < 10; i = i + 1) t = t + s[i]; t\n");
+    assert_backends_agree("define f(x[], n) { x[0] = 9; return (x[n] + x[0]) }; a[0] = 1; a[4] = 2; f(a[], 4); a[0]\n");
+    assert_backends_agree("define g(n) { auto b[]; b[n] = n; if (n > 0) return (g(n - 1) + b[n]); return b[n] }; g(5); b[5]\n");
+}